@@ -0,0 +1,297 @@
+// blocklight.rs
+// luz por bloques estilo Minecraft: niveles 0..15 de luz de cielo y de bloque
+// que se inundan por el aire y los bloques transparentes, perdiendo 1 por bloque.
+// Se usa como iluminacion indirecta barata (dentro de la casa, bajo los arboles)
+use raylib::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use crate::color::Color;
+use crate::cube::Cube;
+
+pub const MAX_LEVEL: u8 = 15;
+// borde de aire alrededor de la escena para que la luz rodee los bloques
+const MARGIN: i32 = 2;
+// brillo de una esquina segun cuantos vecinos la tapan (3 = ninguno)
+const AO_LEVELS: [f32; 4] = [0.35, 0.55, 0.75, 1.0];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Air,
+    // deja pasar la luz perdiendo cost niveles (vidrio 1, agua 2, hojas 3)
+    Transparent { cost: u8 },
+    Solid,
+}
+
+pub struct LightGrid {
+    min: (i32, i32, i32),
+    size: (usize, usize, usize),
+    cells: Vec<Cell>,
+    sky: Vec<u8>,
+    block: Vec<u8>,
+    // color de la fuente que dejo el nivel de bloque mas alto en cada celda
+    block_color: Vec<Color>,
+    // para saber si los bloques cambiaron desde la ultima vez
+    signature: u64,
+}
+
+impl LightGrid {
+    pub fn build(cubes: &[Cube]) -> Self {
+        let mut grid = LightGrid { signature: signature(cubes), ..LightGrid::empty() };
+        grid.compute(cubes);
+        grid
+    }
+
+    // recalcular solo si cambiaron los bloques; devuelve si recalculo
+    pub fn refresh(&mut self, cubes: &[Cube]) -> bool {
+        let signature = signature(cubes);
+        if signature == self.signature {
+            return false;
+        }
+        self.signature = signature;
+        self.compute(cubes);
+        true
+    }
+
+    fn compute(&mut self, cubes: &[Cube]) {
+        if cubes.is_empty() {
+            *self = LightGrid { signature: self.signature, ..LightGrid::empty() };
+            return;
+        }
+
+        // caja de la escena en celdas (centros enteros)
+        let mut lo = (i32::MAX, i32::MAX, i32::MAX);
+        let mut hi = (i32::MIN, i32::MIN, i32::MIN);
+        for cube in cubes {
+            let (x, y, z) = cell_of(cube.center);
+            lo = (lo.0.min(x), lo.1.min(y), lo.2.min(z));
+            hi = (hi.0.max(x), hi.1.max(y), hi.2.max(z));
+        }
+        self.min = (lo.0 - MARGIN, lo.1 - MARGIN, lo.2 - MARGIN);
+        self.size = (
+            (hi.0 - lo.0 + 1 + 2 * MARGIN) as usize,
+            (hi.1 - lo.1 + 1 + 2 * MARGIN) as usize,
+            (hi.2 - lo.2 + 1 + 2 * MARGIN) as usize,
+        );
+        let count = self.size.0 * self.size.1 * self.size.2;
+        self.cells = vec![Cell::Air; count];
+        self.sky = vec![0; count];
+        self.block = vec![0; count];
+        self.block_color = vec![Color::black(); count];
+
+        // bloques que llenan su celda; los chicos (rocas, la fogata) no tapan la luz
+        let mut sources: HashMap<usize, Color> = HashMap::new();
+        for cube in cubes {
+            let Some(i) = self.index(cell_of(cube.center)) else { continue };
+            if let Some(emission) = cube.material.emission {
+                let strength = emission.r.max(emission.g).max(emission.b).max(1e-4);
+                sources.insert(i, emission * (1.0 / strength));
+                continue;
+            }
+            if cube.size < 0.9 {
+                continue;
+            }
+            let m = &cube.material;
+            let cell = if m.is_opaque() {
+                Cell::Solid
+            } else {
+                Cell::Transparent { cost: 1 + ((1.0 - m.transparency) * 2.0).round() as u8 }
+            };
+            // si hay dos bloques en la misma celda gana el que mas tapa
+            if self.cells[i] != Cell::Solid {
+                self.cells[i] = cell;
+            }
+        }
+
+        // luz de cielo: baja derecho sin perder nivel por el aire hasta el primer bloque
+        let mut queue = VecDeque::new();
+        for x in 0..self.size.0 {
+            for z in 0..self.size.2 {
+                let mut level = MAX_LEVEL;
+                for y in (0..self.size.1).rev() {
+                    let i = self.flat(x, y, z);
+                    match self.cells[i] {
+                        Cell::Solid => break,
+                        Cell::Transparent { cost } => level = level.saturating_sub(cost),
+                        Cell::Air => {}
+                    }
+                    if level == 0 { break; }
+                    self.sky[i] = level;
+                    queue.push_back(i);
+                }
+            }
+        }
+        let sky = std::mem::take(&mut self.sky);
+        self.sky = self.flood(sky, queue, None);
+
+        // luz de bloque desde los emisivos
+        let mut block = std::mem::take(&mut self.block);
+        let mut queue = VecDeque::new();
+        for (&i, &color) in &sources {
+            block[i] = MAX_LEVEL;
+            self.block_color[i] = color;
+            queue.push_back(i);
+        }
+        let mut colors = std::mem::take(&mut self.block_color);
+        self.block = self.flood(block, queue, Some(&mut colors));
+        self.block_color = colors;
+    }
+
+    // esparcir niveles a los vecinos: -1 por paso (mas el costo del bloque transparente)
+    fn flood(&self, mut levels: Vec<u8>, mut queue: VecDeque<usize>, mut colors: Option<&mut Vec<Color>>) -> Vec<u8> {
+        while let Some(i) = queue.pop_front() {
+            let level = levels[i];
+            if level <= 1 { continue; }
+            let (x, y, z) = self.coords(i);
+            for (dx, dy, dz) in [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)] {
+                let (nx, ny, nz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+                if nx < 0 || ny < 0 || nz < 0 { continue; }
+                let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+                if nx >= self.size.0 || ny >= self.size.1 || nz >= self.size.2 { continue; }
+                let n = self.flat(nx, ny, nz);
+                let cost = match self.cells[n] {
+                    Cell::Solid => continue,
+                    Cell::Transparent { cost } => 1 + cost,
+                    Cell::Air => 1,
+                };
+                let next = level.saturating_sub(cost);
+                if next > levels[n] {
+                    levels[n] = next;
+                    if let Some(colors) = colors.as_deref_mut() {
+                        colors[n] = colors[i];
+                    }
+                    queue.push_back(n);
+                }
+            }
+        }
+        levels
+    }
+
+    fn empty() -> Self {
+        LightGrid {
+            min: (0, 0, 0),
+            size: (0, 0, 0),
+            cells: Vec::new(),
+            sky: Vec::new(),
+            block: Vec::new(),
+            block_color: Vec::new(),
+            signature: 0,
+        }
+    }
+
+    // luz en un punto de una superficie (espacio del diorama): se mira el aire delante
+    // de la cara, con interpolacion suave entre celdas que ignora las solidas.
+    // Devuelve el brillo de cielo en [0, 1] y la luz de bloque con su color
+    pub fn sample(&self, point: Vector3, normal: Vector3) -> (f32, Color) {
+        let p = point + normal * 0.5;
+        let (bx, by, bz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (fx, fy, fz) = (p.x - bx, p.y - by, p.z - bz);
+
+        let mut sky = 0.0;
+        let mut block = Color::black();
+        let mut total = 0.0;
+        for corner in 0..8 {
+            let (ox, oy, oz) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let w = (if ox == 1 { fx } else { 1.0 - fx })
+                * (if oy == 1 { fy } else { 1.0 - fy })
+                * (if oz == 1 { fz } else { 1.0 - fz });
+            if w <= 0.0 { continue; }
+            let cell = (bx as i32 + ox, by as i32 + oy, bz as i32 + oz);
+            let (sky_level, block_level, color) = match self.index(cell) {
+                Some(i) if self.cells[i] == Cell::Solid => continue,
+                Some(i) => (self.sky[i], self.block[i], self.block_color[i]),
+                // fuera de la grilla: cielo abierto
+                None => (MAX_LEVEL, 0, Color::black()),
+            };
+            sky += brightness(sky_level) * w;
+            block = block + color * (brightness(block_level) * w);
+            total += w;
+        }
+        if total <= 0.0 {
+            return (0.0, Color::black());
+        }
+        (sky / total, block * (1.0 / total))
+    }
+
+    // oclusion ambiental por vecinos como en Minecraft: en cada esquina de la cara se
+    // cuentan los dos bloques de costado y el de la diagonal delante de la cara
+    // (los 8 vecinos), y se interpola entre esquinas. 1 = sin ocluir
+    pub fn voxel_ao(&self, point: Vector3, normal: Vector3) -> f32 {
+        // eje dominante de la normal y dos ejes en el plano de la cara
+        let (n, t1, t2) = if normal.x.abs() >= normal.y.abs() && normal.x.abs() >= normal.z.abs() {
+            ((normal.x.signum() as i32, 0, 0), (0, 1, 0), (0, 0, 1))
+        } else if normal.y.abs() >= normal.z.abs() {
+            ((0, normal.y.signum() as i32, 0), (0, 0, 1), (1, 0, 0))
+        } else {
+            ((0, 0, normal.z.signum() as i32), (1, 0, 0), (0, 1, 0))
+        };
+        // celda de aire delante de la cara (un cuarto de bloque sirve tambien para el
+        // piso, que esta corrido medio bloque)
+        let front = point + Vector3::new(n.0 as f32, n.1 as f32, n.2 as f32) * 0.25;
+        let air = cell_of(front);
+        let solid = |a: i32, b: i32| {
+            let cell = (air.0 + t1.0 * a + t2.0 * b, air.1 + t1.1 * a + t2.1 * b, air.2 + t1.2 * a + t2.2 * b);
+            matches!(self.index(cell), Some(i) if self.cells[i] == Cell::Solid)
+        };
+        let corner = |a: i32, b: i32| {
+            let (side1, side2) = (solid(a, 0), solid(0, b));
+            let level = if side1 && side2 { 0 } else { 3 - side1 as usize - side2 as usize - solid(a, b) as usize };
+            AO_LEVELS[level]
+        };
+
+        // posicion dentro de la cara en [0, 1]
+        let center = Vector3::new(air.0 as f32, air.1 as f32, air.2 as f32);
+        let axis = |t: (i32, i32, i32)| Vector3::new(t.0 as f32, t.1 as f32, t.2 as f32);
+        let fu = ((point - center).dot(axis(t1)) + 0.5).clamp(0.0, 1.0);
+        let fv = ((point - center).dot(axis(t2)) + 0.5).clamp(0.0, 1.0);
+        let low = corner(-1, -1) * (1.0 - fu) + corner(1, -1) * fu;
+        let high = corner(-1, 1) * (1.0 - fu) + corner(1, 1) * fu;
+        low * (1.0 - fv) + high * fv
+    }
+
+    fn index(&self, (x, y, z): (i32, i32, i32)) -> Option<usize> {
+        let (x, y, z) = (x - self.min.0, y - self.min.1, z - self.min.2);
+        if x < 0 || y < 0 || z < 0 { return None; }
+        let (x, y, z) = (x as usize, y as usize, z as usize);
+        if x >= self.size.0 || y >= self.size.1 || z >= self.size.2 { return None; }
+        Some(self.flat(x, y, z))
+    }
+
+    fn flat(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.size.2 + z) * self.size.0 + x
+    }
+
+    fn coords(&self, i: usize) -> (usize, usize, usize) {
+        let x = i % self.size.0;
+        let z = (i / self.size.0) % self.size.2;
+        let y = i / (self.size.0 * self.size.2);
+        (x, y, z)
+    }
+}
+
+// celda con centro entero que contiene el centro del cubo
+fn cell_of(p: Vector3) -> (i32, i32, i32) {
+    (p.x.round() as i32, p.y.round() as i32, p.z.round() as i32)
+}
+
+// curva de brillo de Minecraft: nivel 15 = 1, cae rapido en los niveles bajos
+fn brightness(level: u8) -> f32 {
+    let f = level as f32 / MAX_LEVEL as f32;
+    f / (4.0 - 3.0 * f)
+}
+
+// resumen barato de los bloques que afectan la luz (tambien lo usa el mapa de fotones)
+pub(crate) fn signature(cubes: &[Cube]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    let mut mix = |v: u64| {
+        h ^= v;
+        h = h.wrapping_mul(0x100000001b3);
+    };
+    for cube in cubes {
+        mix(cube.center.x.to_bits() as u64);
+        mix(cube.center.y.to_bits() as u64);
+        mix(cube.center.z.to_bits() as u64);
+        mix(cube.size.to_bits() as u64);
+        mix(cube.material.transparency.to_bits() as u64);
+        mix(cube.material.emission.is_some() as u64);
+    }
+    h
+}
@@ -1,24 +1,47 @@
 // camera.rs
 use raylib::prelude::*;
-use crate::ray::Ray;
+use crate::ray::{Ray, RayDifferential};
 
 pub struct Camera {
     pub position: Vector3,
     pub look_at: Vector3,
     pub fov: f32,
+    // exposicion con la que las luces en unidades fisicas pasan a unidades de la escena
+    pub ev100: f32,
+    // ajuste en pasos (stops) sobre la imagen final
+    pub exposure_compensation: f32,
 }
 
+// EV100 de un interior bien iluminado: una lampara de 800 lm queda cerca de las luces relativas
+const DEFAULT_EV100: f32 = 5.0;
+
 impl Camera {
     pub fn new(position: Vector3, look_at: Vector3, fov: f32) -> Self {
-        Camera { position, look_at, fov }
+        Camera { position, look_at, fov, ev100: DEFAULT_EV100, exposure_compensation: 0.0 }
+    }
+
+    // luminancia fisica -> escena: 1 / (1.2 * 2^EV100) (exposicion fotografica estandar)
+    pub fn physical_exposure(&self) -> f32 {
+        1.0 / (1.2 * 2f32.powf(self.ev100))
     }
 
-    pub fn get_ray(&self, u: f32, v: f32) -> Ray {
+    pub fn exposure_gain(&self) -> f32 {
+        2f32.powf(self.exposure_compensation)
+    }
+
+    // du, dv: tamaño de un pixel en coordenadas de pantalla (para los rayos diferenciales)
+    pub fn get_ray(&self, u: f32, v: f32, du: f32, dv: f32) -> Ray {
         let forward = (self.look_at - self.position).normalized();
         let right = Vector3::new(0.0, 1.0, 0.0).cross(forward).normalized();
         let up = forward.cross(right);
 
-        let direction = (forward + right * u + up * v).normalized();
-        Ray::new(self.position, direction)
+        let direction_at = |u: f32, v: f32| (forward + right * u + up * v).normalized();
+        let differential = RayDifferential {
+            rx_origin: self.position,
+            rx_direction: direction_at(u + du, v),
+            ry_origin: self.position,
+            ry_direction: direction_at(u, v + dv),
+        };
+        Ray::new(self.position, direction_at(u, v)).with_differential(Some(differential))
     }
-}
\ No newline at end of file
+}
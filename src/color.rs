@@ -19,6 +19,39 @@ impl Color {
     pub fn white() -> Self {
         Color::new(1.0, 1.0, 1.0)
     }
+
+    // colores elegidos a ojo (en sRGB) pasados a lineal para iluminar
+    pub fn from_srgb(r: f32, g: f32, b: f32) -> Self {
+        Color::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b))
+    }
+
+    // lineal -> sRGB para mostrar en pantalla (recorta a [0, 1])
+    pub fn to_srgb(self) -> Self {
+        Color::new(linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b))
+    }
+
+    // luminancia relativa (Rec. 709) de un color lineal
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+}
+
+// curva sRGB (IEC 61966-2-1) de un canal en [0, 1]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 impl Add for Color {
@@ -8,14 +8,36 @@ pub struct Cube {
     pub center: Vector3,
     pub size: f32,
     pub material: Material,
+    pub casts_shadows: bool,    // tapa la luz de otros objetos
+    pub receives_shadows: bool, // se sombrea (si no, le llega toda la luz)
 }
 
 impl Cube {
     pub fn new(center: Vector3, size: f32, material: Material) -> Self {
-        Cube { center, size, material }
+        Cube { center, size, material, casts_shadows: true, receives_shadows: true }
+    }
+
+    pub fn with_shadows(mut self, casts: bool, receives: bool) -> Self {
+        self.casts_shadows = casts;
+        self.receives_shadows = receives;
+        self
     }
 
     pub fn intersect_with_uv(&self, ray: &Ray) -> Option<(f32, f32, f32)> {
+        let (tmin, _) = self.intersect_interval(ray)?;
+
+        if tmin <= 0.0 {
+            return None;
+        }
+
+        // calcular UV
+        let (u, v) = self.uv_at(ray.at(tmin));
+
+        Some((tmin, u, v))
+    }
+
+    // t de entrada y de salida del rayo (la entrada puede ser negativa si el origen esta adentro)
+    pub fn intersect_interval(&self, ray: &Ray) -> Option<(f32, f32)> {
         let half_size = self.size / 2.0;
         let min = self.center - Vector3::new(half_size, half_size, half_size);
         let max = self.center + Vector3::new(half_size, half_size, half_size);
@@ -53,19 +75,15 @@ impl Cube {
         }
 
         tmin = tmin.max(tzmin);
+        tmax = tmax.min(tzmax);
 
-        if tmin <= 0.0 {
-            return None;
-        }
+        Some((tmin, tmax))
+    }
 
-        // calcular UV
-        let hit_point = ray.at(tmin);
-        let local = hit_point - self.center;
-        
+    // UV de un punto sobre la superficie del cubo
+    pub fn uv_at(&self, point: Vector3) -> (f32, f32) {
         // determinar qué cara fue golpeada y calcular UVs
-        let (u, v) = self.calculate_uv(local);
-
-        Some((tmin, u, v))
+        self.calculate_uv(point - self.center)
     }
 
     fn calculate_uv(&self, local: Vector3) -> (f32, f32) {
@@ -77,15 +95,28 @@ impl Cube {
 
         let min_dist = dx.min(dy).min(dz);
         
+        // en las caras laterales v = 0 queda arriba del bloque (borde de pasto, etc.)
         if min_dist == dx {
             // X 
-            ((local.z + half_size) / self.size, (local.y + half_size) / self.size)
+            ((local.z + half_size) / self.size, (half_size - local.y) / self.size)
         } else if min_dist == dy {
             // Y 
             ((local.x + half_size) / self.size, (local.z + half_size) / self.size)
         } else {
             // Z 
-            ((local.x + half_size) / self.size, (local.y + half_size) / self.size)
+            ((local.x + half_size) / self.size, (half_size - local.y) / self.size)
+        }
+    }
+
+    // cambio de (u, v) para un desplazamiento dp sobre la cara con esta normal
+    // (mismas convenciones que calculate_uv)
+    pub fn uv_derivatives(&self, normal: Vector3, dp: Vector3) -> (f32, f32) {
+        if normal.x != 0.0 {
+            (dp.z / self.size, -dp.y / self.size)
+        } else if normal.y != 0.0 {
+            (dp.x / self.size, dp.z / self.size)
+        } else {
+            (dp.x / self.size, -dp.y / self.size)
         }
     }
 
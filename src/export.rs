@@ -0,0 +1,450 @@
+// export.rs
+// exportar la geometria del diorama a OBJ+MTL y glTF 2.0
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Result;
+use std::path::Path;
+use crate::cube::Cube;
+use crate::material::{AlphaMode, Material, MaterialType};
+use crate::mesh::Mesh;
+use crate::texture::TextureManager;
+
+// triangulos agrupados por material
+struct Group {
+    name: String,
+    material: Material,
+    texture: Option<String>, // textura de la cara, puede diferir de material.texture_name
+    positions: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    uvs: Vec<(f32, f32)>, // convencion del raytracer: v = 0 arriba
+    indices: Vec<u32>,
+}
+
+impl Group {
+    fn push_vertex(&mut self, p: Vector3, n: Vector3, uv: (f32, f32)) -> u32 {
+        self.positions.push(p);
+        self.normals.push(n);
+        self.uvs.push(uv);
+        (self.positions.len() - 1) as u32
+    }
+}
+
+// caras del cubo: normal y ejes tangentes con a x b = normal
+const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+    ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+];
+
+fn vec3(a: [f32; 3]) -> Vector3 {
+    Vector3::new(a[0], a[1], a[2])
+}
+
+// firma para agrupar cubos con el mismo material
+fn material_key(m: &Material) -> String {
+    format!(
+        "{:?}|{:?}|{:?}|{}|{}|{}|{}|{:?}|{:?}",
+        m.texture_name, m.material_type, m.albedo, m.specular, m.reflectivity, m.transparency, m.refractive_index, m.emission,
+        m.alpha_mode
+    )
+}
+
+fn grid_key(p: Vector3, size: f32) -> (i64, i64, i64, u32) {
+    let q = |v: f32| (v / size * 1000.0).round() as i64;
+    (q(p.x), q(p.y), q(p.z), size.to_bits())
+}
+
+// juntar cubos y meshes en grupos por material y textura, sin caras ocultas entre bloques vecinos
+fn collect_geometry(cubes: &[Cube], meshes: &[Mesh], textures: &TextureManager) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    let mut group_for = |m: &Material, texture: Option<&str>, groups: &mut Vec<Group>| -> usize {
+        let key = format!("{}|{:?}", material_key(m), texture);
+        if let Some(&i) = by_key.get(&key) {
+            return i;
+        }
+        // "atlas:tile" -> "atlas_tile" para que sea un nombre valido
+        let base = texture.map(|t| t.replace(':', "_")).unwrap_or_else(|| {
+            format!("color_{:02x}{:02x}{:02x}",
+                (m.albedo.r.clamp(0.0, 1.0) * 255.0) as u8,
+                (m.albedo.g.clamp(0.0, 1.0) * 255.0) as u8,
+                (m.albedo.b.clamp(0.0, 1.0) * 255.0) as u8)
+        });
+        let mut name = base.clone();
+        let mut n = 2;
+        while groups.iter().any(|g| g.name == name) {
+            name = format!("{}_{}", base, n);
+            n += 1;
+        }
+        groups.push(Group {
+            name,
+            material: m.clone(),
+            texture: texture.map(|t| t.to_string()),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+        });
+        by_key.insert(key, groups.len() - 1);
+        groups.len() - 1
+    };
+
+    let mut occupied: HashMap<(i64, i64, i64, u32), &Cube> = HashMap::new();
+    for cube in cubes {
+        occupied.insert(grid_key(cube.center, cube.size), cube);
+    }
+
+    for cube in cubes {
+        let h = cube.size / 2.0;
+        for (axis, &(n, a, b)) in FACES.iter().enumerate() {
+            let (n, a, b) = (vec3(n), vec3(a), vec3(b));
+
+            // cara tapada: vecino opaco del mismo tamaño, o mismo material transparente (agua-agua)
+            if let Some(other) = occupied.get(&grid_key(cube.center + n * cube.size, cube.size)) {
+                let hidden = other.material.is_opaque()
+                    || material_key(&other.material) == material_key(&cube.material);
+                if hidden { continue; }
+            }
+
+            let texture = cube.material.texture_for_face(n, textures);
+            let rect = texture.and_then(|t| textures.uv_rect(t));
+            let gi = group_for(&cube.material, texture, &mut groups);
+            let group = &mut groups[gi];
+            let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+            let mut idx = [0u32; 4];
+            for (k, (sa, sb)) in corners.iter().enumerate() {
+                let local = n * h + a * (sa * h) + b * (sb * h);
+                // mismas UVs que Cube::calculate_uv
+                let uv = match axis / 2 {
+                    0 => ((local.z + h) / cube.size, (h - local.y) / cube.size),
+                    1 => ((local.x + h) / cube.size, (local.z + h) / cube.size),
+                    _ => ((local.x + h) / cube.size, (h - local.y) / cube.size),
+                };
+                let uv = atlas_uv(uv, rect);
+                idx[k] = group.push_vertex(cube.center + local, n, uv);
+            }
+            group.indices.extend_from_slice(&[idx[0], idx[1], idx[2], idx[0], idx[2], idx[3]]);
+        }
+    }
+
+    for mesh in meshes {
+        let rect = mesh.material.texture_name.as_deref().and_then(|t| textures.uv_rect(t));
+        let gi = group_for(&mesh.material, mesh.material.texture_name.as_deref(), &mut groups);
+        let group = &mut groups[gi];
+        for tri in &mesh.triangles {
+            let verts = [(tri.v0, tri.uv0), (tri.v1, tri.uv1), (tri.v2, tri.uv2)];
+            for (k, (v, uv)) in verts.iter().enumerate() {
+                // el mesh voltea v al muestrear; sin UVs usa baricentricas
+                let uv = match uv {
+                    Some(t) => (t.x, 1.0 - t.y),
+                    None => [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)][k],
+                };
+                let uv = atlas_uv(uv, rect);
+                let i = group.push_vertex(mesh.position + *v * mesh.scale, tri.n, uv);
+                group.indices.push(i);
+            }
+        }
+    }
+
+    groups.retain(|g| !g.indices.is_empty());
+    groups
+}
+
+// llevar una UV del tile al rectangulo que ocupa en el atlas
+fn atlas_uv(uv: (f32, f32), rect: Option<[f32; 4]>) -> (f32, f32) {
+    match rect {
+        Some(r) => (r[0] + uv.0 * (r[2] - r[0]), r[1] + uv.1 * (r[3] - r[1])),
+        None => uv,
+    }
+}
+
+// copiar los PNG usados a <dir>/textures y devolver nombre -> ruta relativa
+fn copy_textures(out_dir: &Path, groups: &[Group], textures: &TextureManager) -> Result<HashMap<String, String>> {
+    let mut copied = HashMap::new();
+    for g in groups {
+        let Some(name) = &g.texture else { continue };
+        if copied.contains_key(name) { continue; }
+        let Some(src) = textures.texture_path(name) else { continue };
+        let file_name = Path::new(src).file_name().map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("{}.png", name));
+        std::fs::create_dir_all(out_dir.join("textures"))?;
+        std::fs::copy(src, out_dir.join("textures").join(&file_name))?;
+        copied.insert(name.clone(), format!("textures/{}", file_name));
+    }
+    Ok(copied)
+}
+
+// cadena JSON con comillas, escapando lo que romperia el archivo
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn output_dir(path: &str) -> Result<&Path> {
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    if !dir.as_os_str().is_empty() {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(dir)
+}
+
+// OBJ + MTL (el .mtl queda junto al .obj con el mismo nombre)
+pub fn export_obj(path: &str, cubes: &[Cube], meshes: &[Mesh], textures: &TextureManager) -> Result<()> {
+    let groups = collect_geometry(cubes, meshes, textures);
+    let dir = output_dir(path)?;
+    let texture_paths = copy_textures(dir, &groups, textures)?;
+    let mtl_path = Path::new(path).with_extension("mtl");
+    let mtl_name = mtl_path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let mut obj = String::new();
+    let mut mtl = String::new();
+    let _ = writeln!(obj, "# diorama exportado por el raytracer");
+    let _ = writeln!(obj, "mtllib {}", mtl_name);
+
+    let mut offset = 1u32;
+    for g in &groups {
+        let _ = writeln!(obj, "o {}", g.name);
+        for p in &g.positions {
+            let _ = writeln!(obj, "v {} {} {}", p.x, p.y, p.z);
+        }
+        for (u, v) in &g.uvs {
+            // OBJ usa v = 0 abajo
+            let _ = writeln!(obj, "vt {} {}", u, 1.0 - v);
+        }
+        for n in &g.normals {
+            let _ = writeln!(obj, "vn {} {} {}", n.x, n.y, n.z);
+        }
+        let _ = writeln!(obj, "usemtl {}", g.name);
+        for tri in g.indices.chunks(3) {
+            let (a, b, c) = (tri[0] + offset, tri[1] + offset, tri[2] + offset);
+            let _ = writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}");
+        }
+        offset += g.positions.len() as u32;
+
+        let m = &g.material;
+        let _ = writeln!(mtl, "newmtl {}", g.name);
+        let _ = writeln!(mtl, "Kd {} {} {}", m.albedo.r, m.albedo.g, m.albedo.b);
+        let _ = writeln!(mtl, "Ks {} {} {}", m.specular, m.specular, m.specular);
+        let _ = writeln!(mtl, "Ns 32");
+        let _ = writeln!(mtl, "Ni {}", m.refractive_index);
+        let _ = writeln!(mtl, "d {}", 1.0 - m.transparency);
+        if let Some(e) = m.emission {
+            let _ = writeln!(mtl, "Ke {} {} {}", e.r, e.g, e.b);
+        }
+        // illum 3 = reflexion trazada, 7 = refraccion
+        let illum = if m.transparency > 0.0 { 7 } else if m.reflectivity > 0.0 { 3 } else { 2 };
+        let _ = writeln!(mtl, "illum {}", illum);
+        if let Some(tex) = g.texture.as_ref().and_then(|t| texture_paths.get(t)) {
+            let _ = writeln!(mtl, "map_Kd {}", tex);
+            if m.alpha_mode != AlphaMode::Opaque {
+                // el alfa sale del mismo PNG
+                let _ = writeln!(mtl, "map_d {}", tex);
+            }
+        }
+        let _ = writeln!(mtl);
+    }
+
+    std::fs::write(path, obj)?;
+    std::fs::write(mtl_path, mtl)?;
+    Ok(())
+}
+
+// glTF 2.0: .gltf (JSON) + .bin con los buffers
+pub fn export_gltf(path: &str, cubes: &[Cube], meshes: &[Mesh], textures: &TextureManager) -> Result<()> {
+    let groups = collect_geometry(cubes, meshes, textures);
+    let dir = output_dir(path)?;
+    let texture_paths = copy_textures(dir, &groups, textures)?;
+    let bin_path = Path::new(path).with_extension("bin");
+    let bin_name = bin_path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+
+    let mut bin: Vec<u8> = Vec::new();
+    let mut buffer_views: Vec<String> = Vec::new();
+    let mut accessors: Vec<String> = Vec::new();
+    let mut primitives: Vec<String> = Vec::new();
+    let mut materials: Vec<String> = Vec::new();
+    let mut images: Vec<String> = Vec::new();
+    let mut gltf_textures: Vec<String> = Vec::new();
+    let mut image_index: HashMap<String, usize> = HashMap::new();
+    let mut extensions: Vec<&str> = Vec::new();
+
+    // agrega un bufferView + accessor y devuelve el indice del accessor
+    let mut push_accessor = |bin: &mut Vec<u8>, data: Vec<u8>, target: u32, component: u32, count: usize, kind: &str, bounds: Option<(Vector3, Vector3)>| -> usize {
+        let offset = bin.len();
+        bin.extend_from_slice(&data);
+        buffer_views.push(format!(
+            "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":{}}}",
+            offset, data.len(), target
+        ));
+        let minmax = match bounds {
+            Some((lo, hi)) => format!(",\"min\":[{},{},{}],\"max\":[{},{},{}]", lo.x, lo.y, lo.z, hi.x, hi.y, hi.z),
+            None => String::new(),
+        };
+        accessors.push(format!(
+            "{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"{}\"{}}}",
+            buffer_views.len() - 1, component, count, kind, minmax
+        ));
+        accessors.len() - 1
+    };
+
+    for (i, g) in groups.iter().enumerate() {
+        let mut lo = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut hi = Vector3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        let mut pos_bytes = Vec::with_capacity(g.positions.len() * 12);
+        for p in &g.positions {
+            lo = Vector3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z));
+            hi = Vector3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z));
+            for c in [p.x, p.y, p.z] { pos_bytes.extend_from_slice(&c.to_le_bytes()); }
+        }
+        let mut nrm_bytes = Vec::with_capacity(g.normals.len() * 12);
+        for n in &g.normals {
+            for c in [n.x, n.y, n.z] { nrm_bytes.extend_from_slice(&c.to_le_bytes()); }
+        }
+        let mut uv_bytes = Vec::with_capacity(g.uvs.len() * 8);
+        for (u, v) in &g.uvs {
+            // glTF tambien usa v = 0 arriba
+            for c in [*u, *v] { uv_bytes.extend_from_slice(&c.to_le_bytes()); }
+        }
+        let mut idx_bytes = Vec::with_capacity(g.indices.len() * 4);
+        for idx in &g.indices { idx_bytes.extend_from_slice(&idx.to_le_bytes()); }
+
+        // 34962 = ARRAY_BUFFER, 34963 = ELEMENT_ARRAY_BUFFER, 5126 = FLOAT, 5125 = UNSIGNED_INT
+        let count = g.positions.len();
+        let pos = push_accessor(&mut bin, pos_bytes, 34962, 5126, count, "VEC3", Some((lo, hi)));
+        let nrm = push_accessor(&mut bin, nrm_bytes, 34962, 5126, count, "VEC3", None);
+        let uv = push_accessor(&mut bin, uv_bytes, 34962, 5126, count, "VEC2", None);
+        let idx = push_accessor(&mut bin, idx_bytes, 34963, 5125, g.indices.len(), "SCALAR", None);
+        primitives.push(format!(
+            "{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{},\"TEXCOORD_0\":{}}},\"indices\":{},\"material\":{}}}",
+            pos, nrm, uv, idx, i
+        ));
+
+        let m = &g.material;
+        let metallic = if m.material_type == MaterialType::Reflective { m.reflectivity } else { 0.0 };
+        let roughness = (1.0 - m.specular).clamp(0.05, 1.0);
+        let mut pbr = format!(
+            "\"baseColorFactor\":[{},{},{},1],\"metallicFactor\":{},\"roughnessFactor\":{}",
+            m.albedo.r, m.albedo.g, m.albedo.b, metallic, roughness
+        );
+        if let Some(uri) = g.texture.as_ref().and_then(|t| texture_paths.get(t)) {
+            let tex = *image_index.entry(uri.clone()).or_insert_with(|| {
+                images.push(format!("{{\"uri\":{}}}", json_string(uri)));
+                // sampler 0: nearest + repeat, como el raytracer
+                gltf_textures.push(format!("{{\"sampler\":0,\"source\":{}}}", images.len() - 1));
+                gltf_textures.len() - 1
+            });
+            let _ = write!(pbr, ",\"baseColorTexture\":{{\"index\":{}}}", tex);
+        }
+
+        let mut mat = format!("{{\"name\":{},\"pbrMetallicRoughness\":{{{}}}", json_string(&g.name), pbr);
+        let mut mat_ext: Vec<String> = Vec::new();
+        if let Some(e) = m.emission {
+            // glTF limita emissiveFactor a [0,1]; el resto va en emissive_strength
+            let strength = e.r.max(e.g).max(e.b).max(1.0);
+            let _ = write!(mat, ",\"emissiveFactor\":[{},{},{}]", e.r / strength, e.g / strength, e.b / strength);
+            if strength > 1.0 {
+                mat_ext.push(format!("\"KHR_materials_emissive_strength\":{{\"emissiveStrength\":{}}}", strength));
+                extensions.push("KHR_materials_emissive_strength");
+            }
+        }
+        match m.alpha_mode {
+            AlphaMode::Cutout(cutoff) => {
+                let _ = write!(mat, ",\"alphaMode\":\"MASK\",\"alphaCutoff\":{},\"doubleSided\":true", cutoff);
+            }
+            // BLEND solo para el alfa de la textura; lo refractivo va por transmission
+            AlphaMode::Blend => mat.push_str(",\"alphaMode\":\"BLEND\""),
+            AlphaMode::Opaque => {}
+        }
+        if m.transparency > 0.0 {
+            mat_ext.push(format!("\"KHR_materials_transmission\":{{\"transmissionFactor\":{}}}", m.transparency));
+            mat_ext.push(format!("\"KHR_materials_ior\":{{\"ior\":{}}}", m.refractive_index));
+            extensions.push("KHR_materials_transmission");
+            extensions.push("KHR_materials_ior");
+        }
+        if !mat_ext.is_empty() {
+            let _ = write!(mat, ",\"extensions\":{{{}}}", mat_ext.join(","));
+        }
+        mat.push('}');
+        materials.push(mat);
+    }
+
+    extensions.sort();
+    extensions.dedup();
+    let mut json = String::new();
+    json.push_str("{\"asset\":{\"version\":\"2.0\",\"generator\":\"Diorama Raytracer\"}");
+    if !extensions.is_empty() {
+        let list: Vec<String> = extensions.iter().map(|e| format!("\"{}\"", e)).collect();
+        let _ = write!(json, ",\"extensionsUsed\":[{}]", list.join(","));
+    }
+    json.push_str(",\"scene\":0,\"scenes\":[{\"nodes\":[0]}]");
+    json.push_str(",\"nodes\":[{\"name\":\"diorama\",\"mesh\":0}]");
+    let _ = write!(json, ",\"meshes\":[{{\"name\":\"diorama\",\"primitives\":[{}]}}]", primitives.join(","));
+    let _ = write!(json, ",\"materials\":[{}]", materials.join(","));
+    if !images.is_empty() {
+        // 9728 = NEAREST, 10497 = REPEAT
+        json.push_str(",\"samplers\":[{\"magFilter\":9728,\"minFilter\":9728,\"wrapS\":10497,\"wrapT\":10497}]");
+        let _ = write!(json, ",\"images\":[{}]", images.join(","));
+        let _ = write!(json, ",\"textures\":[{}]", gltf_textures.join(","));
+    }
+    let _ = write!(json, ",\"accessors\":[{}]", accessors.join(","));
+    let _ = write!(json, ",\"bufferViews\":[{}]", buffer_views.join(","));
+    let _ = write!(json, ",\"buffers\":[{{\"uri\":{},\"byteLength\":{}}}]", json_string(&bin_name), bin.len());
+    json.push('}');
+
+    std::fs::write(&bin_path, &bin)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(json_string("line\nx\u{1}"), "\"line\\nx\\u0001\"");
+    }
+
+    #[test]
+    fn gltf_materials_use_transmission_without_blend() {
+        let odd = Material::new(MaterialType::Diffuse, Color::new(0.5, 0.5, 0.5), 0.1, 0.0, 0.0, 1.0, None, Some("odd\"name".to_string()), false);
+        let cubes = vec![
+            Cube::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Material::water()),
+            Cube::new(Vector3::new(3.0, 0.0, 0.0), 1.0, odd),
+        ];
+        let dir = std::env::temp_dir().join(format!("diorama-export-{}", std::process::id()));
+        let path = dir.join("scene.gltf");
+        export_gltf(path.to_str().unwrap(), &cubes, &[], &TextureManager::new()).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(json.contains("\"name\":\"odd\\\"name\""));
+        assert!(json.contains("\"KHR_materials_transmission\":{\"transmissionFactor\":0.3}"));
+        // el agua es Opaque: la transparencia no se cuenta dos veces
+        assert!(!json.contains("BLEND"));
+        // baseColorFactor es lineal, igual que el albedo del material
+        let water = Material::water().albedo;
+        assert!(json.contains(&format!("\"baseColorFactor\":[{},{},{},1]", water.r, water.g, water.b)));
+    }
+}
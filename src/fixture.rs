@@ -0,0 +1,44 @@
+// fixture.rs
+// utilidades compartidas por los tests de los importadores (vox, nbt, schematic)
+use crate::cube::Cube;
+
+// archivo temporal con los bytes dados; se borra al terminar el test
+pub struct TempFile {
+    pub path: String,
+}
+
+impl TempFile {
+    pub fn new(name: &str, data: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("diorama-test-{}-{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        TempFile { path: path.to_string_lossy().into_owned() }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+// enteros de 32 bits en little endian (formato .vox)
+pub fn le_i32s(values: &[i32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+// comprimir con gzip, como vienen las estructuras y schematics de Minecraft
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    std::io::Write::write_all(&mut gz, data).unwrap();
+    gz.finish().unwrap()
+}
+
+// el cubo quedo en esa posicion del mundo y con ese tamaño
+pub fn assert_cube(cube: &Cube, center: [f32; 3], size: f32) {
+    let found = [cube.center.x, cube.center.y, cube.center.z];
+    assert!(
+        found.iter().zip(center).all(|(a, b)| (a - b).abs() < 1e-4) && (cube.size - size).abs() < 1e-4,
+        "cubo en {:?} de tamaño {}, se esperaba {:?} de tamaño {}",
+        found, cube.size, center, size
+    );
+}
@@ -0,0 +1,174 @@
+// ibl.rs
+// iluminacion desde el cielo: irradiancia en armonicos esfericos (difuso)
+// y mapas lat-long prefiltrados por rugosidad (reflejos con brillo)
+use raylib::prelude::*;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use crate::color::Color;
+
+// resolucion del cielo muestreado y de los mapas prefiltrados
+const SOURCE_WIDTH: usize = 64;
+const SOURCE_HEIGHT: usize = 32;
+const FILTERED_WIDTH: usize = 32;
+const FILTERED_HEIGHT: usize = 16;
+// rugosidad de cada mapa prefiltrado; rugosidad 0 es el cielo sin filtrar
+const ROUGHNESS_LEVELS: [f32; 4] = [0.2, 0.45, 0.7, 1.0];
+
+// misma convencion que EnvironmentMap: u = 0.5 mira hacia -Z, v = 0 arriba
+pub fn direction_to_latlong(d: Vector3) -> (f32, f32) {
+    let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+    let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+pub fn latlong_to_direction(u: f32, v: f32) -> Vector3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    Vector3::new(theta.sin() * phi.sin(), theta.cos(), -theta.sin() * phi.cos())
+}
+
+// imagen lat-long chica con filtrado bilinear
+struct LatLong {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl LatLong {
+    fn sample(&self, d: Vector3) -> Color {
+        let (u, v) = direction_to_latlong(d);
+        let x = u * self.width as f32 - 0.5;
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let xa = (x0 as i64).rem_euclid(self.width as i64) as usize;
+        let xb = (xa + 1) % self.width;
+        let ya = y0 as usize;
+        let yb = (ya + 1).min(self.height - 1);
+        let p = |x: usize, y: usize| self.pixels[y * self.width + x];
+        let top = p(xa, ya) * (1.0 - fx) + p(xb, ya) * fx;
+        let bottom = p(xa, yb) * (1.0 - fx) + p(xb, yb) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+pub struct Ibl {
+    // coeficientes L2 de la radiancia del cielo
+    sh: [Color; 9],
+    prefiltered: Vec<LatLong>,
+}
+
+// base real de armonicos esfericos hasta l = 2
+pub fn sh_basis(d: Vector3) -> [f32; 9] {
+    let (x, y, z) = (d.x, d.y, d.z);
+    [
+        0.282095,
+        0.488603 * y,
+        0.488603 * z,
+        0.488603 * x,
+        1.092548 * x * y,
+        1.092548 * y * z,
+        0.315392 * (3.0 * z * z - 1.0),
+        1.092548 * x * z,
+        0.546274 * (x * x - y * y),
+    ]
+}
+
+// radiancia difusa saliente para albedo 1 desde coeficientes de radiancia:
+// E(n) / pi (Ramamoorthi y Hanrahan)
+pub fn sh_irradiance(sh: &[Color; 9], normal: Vector3) -> Color {
+    const BAND: [f32; 9] = [
+        PI,
+        2.0 * PI / 3.0, 2.0 * PI / 3.0, 2.0 * PI / 3.0,
+        PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0, PI / 4.0,
+    ];
+    let mut e = Color::black();
+    for ((coeff, basis), band) in sh.iter().zip(sh_basis(normal)).zip(BAND) {
+        e = e + *coeff * (basis * band);
+    }
+    // el truncado a l = 2 puede dar valores negativos con cielos muy contrastados
+    Color::new(e.r.max(0.0), e.g.max(0.0), e.b.max(0.0)) * (1.0 / PI)
+}
+
+impl Ibl {
+    // sin luz de cielo (antes de muestrear el skybox)
+    pub fn empty() -> Self {
+        Ibl { sh: [Color::black(); 9], prefiltered: Vec::new() }
+    }
+
+    // precalcular a partir de cualquier fuente de cielo (cubemap, lat-long, gradiente)
+    pub fn from_sky(sky: impl Fn(&Vector3) -> Color + Sync) -> Self {
+        // cielo muestreado en el centro de cada celda, con su angulo solido
+        let mut source = Vec::with_capacity(SOURCE_WIDTH * SOURCE_HEIGHT);
+        let cell = (2.0 * PI / SOURCE_WIDTH as f32) * (PI / SOURCE_HEIGHT as f32);
+        for y in 0..SOURCE_HEIGHT {
+            let v = (y as f32 + 0.5) / SOURCE_HEIGHT as f32;
+            let solid_angle = cell * (v * PI).sin();
+            for x in 0..SOURCE_WIDTH {
+                let d = latlong_to_direction((x as f32 + 0.5) / SOURCE_WIDTH as f32, v);
+                source.push((d, sky(&d), solid_angle));
+            }
+        }
+
+        let mut sh = [Color::black(); 9];
+        for (d, radiance, solid_angle) in &source {
+            for (coeff, basis) in sh.iter_mut().zip(sh_basis(*d)) {
+                *coeff = *coeff + *radiance * (basis * solid_angle);
+            }
+        }
+
+        // lobulo de Phong (R.L)^n con n sacado de la rugosidad, normalizado por su peso total
+        let prefiltered = ROUGHNESS_LEVELS
+            .iter()
+            .map(|&roughness| {
+                let exponent = (2.0 / (roughness * roughness) - 2.0).max(0.0);
+                let pixels = (0..FILTERED_WIDTH * FILTERED_HEIGHT)
+                    .into_par_iter()
+                    .map(|i| {
+                        let u = (i % FILTERED_WIDTH) as f32 + 0.5;
+                        let v = (i / FILTERED_WIDTH) as f32 + 0.5;
+                        let r = latlong_to_direction(u / FILTERED_WIDTH as f32, v / FILTERED_HEIGHT as f32);
+                        let mut sum = Color::black();
+                        let mut weight = 0.0;
+                        for (d, radiance, solid_angle) in &source {
+                            let cos = r.dot(*d);
+                            if cos <= 0.0 { continue; }
+                            let w = cos.powf(exponent) * solid_angle;
+                            sum = sum + *radiance * w;
+                            weight += w;
+                        }
+                        if weight > 0.0 { sum * (1.0 / weight) } else { Color::black() }
+                    })
+                    .collect();
+                LatLong { width: FILTERED_WIDTH, height: FILTERED_HEIGHT, pixels }
+            })
+            .collect();
+
+        Ibl { sh, prefiltered }
+    }
+
+    // radiancia difusa saliente para albedo 1
+    pub fn irradiance(&self, normal: Vector3) -> Color {
+        sh_irradiance(&self.sh, normal)
+    }
+
+    // cielo filtrado alrededor de la direccion reflejada; sharp es el cielo sin filtrar
+    pub fn prefiltered(&self, direction: Vector3, roughness: f32, sharp: Color) -> Color {
+        if self.prefiltered.is_empty() {
+            return sharp;
+        }
+        let roughness = roughness.clamp(0.0, 1.0);
+        let mut prev_roughness = 0.0;
+        let mut prev = sharp;
+        for (level, &level_roughness) in self.prefiltered.iter().zip(ROUGHNESS_LEVELS.iter()) {
+            let color = level.sample(direction);
+            if roughness <= level_roughness {
+                let f = (roughness - prev_roughness) / (level_roughness - prev_roughness);
+                return prev * (1.0 - f) + color * f;
+            }
+            prev_roughness = level_roughness;
+            prev = color;
+        }
+        prev
+    }
+}
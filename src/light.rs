@@ -1,14 +1,513 @@
 use raylib::prelude::*;
+use std::f32::consts::PI;
 use crate::color::Color;
 
+#[derive(Debug, Clone, Copy)]
+pub enum LightKind {
+    // luz puntual en position, se atenua con la distancia
+    Point,
+    // luz muy lejana (sol, luna): sin atenuacion; angular_radius > 0 da sombras suaves
+    Directional { direction: Vector3, angular_radius: f32 },
+    // foco en position que apunta hacia direction; entre cos_inner y cos_outer el borde se suaviza
+    Spot { direction: Vector3, cos_inner: f32, cos_outer: f32 },
+    // rectangulo centrado en position con lados u_axis y v_axis; emite solo hacia u_axis x v_axis
+    RectArea { u_axis: Vector3, v_axis: Vector3 },
+    // esfera centrada en position
+    SphereArea { radius: f32 },
+    // bloque emisivo centrado en position: se muestrea su superficie, no su interior
+    CubeArea { half_size: f32 },
+}
+
+// como cae la luz con la distancia (no aplica a las direccionales)
+#[derive(Debug, Clone, Copy)]
+pub enum Falloff {
+    None,
+    InverseSquare,
+    // 1 / (constant + linear * d + quadratic * d^2)
+    Polynomial { constant: f32, linear: f32, quadratic: f32 },
+    // inverso al cuadrado que llega suave a 0 en range (ventana de Karis)
+    Windowed { range: f32 },
+}
+
+impl Default for Falloff {
+    // la atenuacion que usaba toda la escena
+    fn default() -> Self {
+        Falloff::Polynomial { constant: 1.0, linear: 0.15, quadratic: 0.05 }
+    }
+}
+
+impl Falloff {
+    pub fn attenuation(&self, dist: f32) -> f32 {
+        // sin singularidad pegado a la luz (1 cm)
+        let dist2 = (dist * dist).max(1e-4);
+        match *self {
+            Falloff::None => 1.0,
+            Falloff::InverseSquare => 1.0 / dist2,
+            Falloff::Polynomial { constant, linear, quadratic } => {
+                1.0 / (constant + linear * dist + quadratic * dist * dist).max(1e-4)
+            }
+            Falloff::Windowed { range } => {
+                let ratio = dist / range.max(1e-4);
+                let window = (1.0 - ratio.powi(4)).clamp(0.0, 1.0);
+                window * window / dist2
+            }
+        }
+    }
+}
+
+// en que unidades esta intensity; las fisicas son solo para luces locales:
+// una direccional no tiene flujo ni intensidad finitos y se da como el cielo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightUnit {
+    // ya en unidades de la escena (como el cielo)
+    Relative,
+    // intensidad luminosa
+    Candela,
+    // flujo total, repartido segun la forma de la luz
+    Lumens,
+}
+
 pub struct Light {
     pub position: Vector3,
     pub color: Color,
     pub intensity: f32,
+    pub kind: LightKind,
+    pub falloff: Falloff,
+    // solo se cambia con with_unit, que revisa que vaya con el tipo de luz
+    unit: LightUnit,
+    // muestras por lado en las luces con tamaño: n x n rayos de sombra (1 = sombra dura)
+    pub shadow_grid: usize,
+}
+
+// una muestra de luz vista desde un punto: cada una lleva su propio rayo de sombra
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub direction: Vector3, // hacia la luz
+    pub distance: f32,      // infinita si es direccional
+    pub weight: f32,        // atenuacion, cono del foco, inclinacion del area
 }
 
+// muestras por luz con tamaño si no se pide otra cosa (2x2 estratificado)
+pub const SOFT_SHADOW_GRID: usize = 2;
+
 impl Light {
     pub fn new(position: Vector3, color: Color, intensity: f32) -> Self {
-        Light { position, color, intensity }
+        Light { position, color, intensity, kind: LightKind::Point, falloff: Falloff::default(), unit: LightUnit::Relative, shadow_grid: SOFT_SHADOW_GRID }
+    }
+
+    // direction apunta hacia la luz
+    pub fn directional(direction: Vector3, color: Color, intensity: f32, angular_radius: f32) -> Self {
+        let direction = direction.normalized();
+        Light {
+            position: direction * 1000.0,
+            color,
+            intensity,
+            kind: LightKind::Directional { direction, angular_radius },
+            falloff: Falloff::default(),
+            unit: LightUnit::Relative,
+            shadow_grid: SOFT_SHADOW_GRID,
+        }
+    }
+
+    // inner y outer son los semiangulos del cono en radianes
+    pub fn spot(position: Vector3, target: Vector3, color: Color, intensity: f32, inner: f32, outer: f32) -> Self {
+        Light {
+            position,
+            color,
+            intensity,
+            kind: LightKind::Spot {
+                direction: (target - position).normalized(),
+                cos_inner: inner.cos(),
+                cos_outer: outer.max(inner).cos(),
+            },
+            falloff: Falloff::default(),
+            unit: LightUnit::Relative,
+            shadow_grid: SOFT_SHADOW_GRID,
+        }
+    }
+
+    pub fn rect_area(center: Vector3, u_axis: Vector3, v_axis: Vector3, color: Color, intensity: f32) -> Self {
+        Light { position: center, color, intensity, kind: LightKind::RectArea { u_axis, v_axis }, falloff: Falloff::default(), unit: LightUnit::Relative, shadow_grid: SOFT_SHADOW_GRID }
+    }
+
+    pub fn sphere_area(center: Vector3, radius: f32, color: Color, intensity: f32) -> Self {
+        Light { position: center, color, intensity, kind: LightKind::SphereArea { radius }, falloff: Falloff::default(), unit: LightUnit::Relative, shadow_grid: SOFT_SHADOW_GRID }
+    }
+
+    // size es la arista del cubo
+    pub fn cube_area(center: Vector3, size: f32, color: Color, intensity: f32) -> Self {
+        Light { position: center, color, intensity, kind: LightKind::CubeArea { half_size: size * 0.5 }, falloff: Falloff::default(), unit: LightUnit::Relative, shadow_grid: SOFT_SHADOW_GRID }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    // en que unidades se lee intensity; la caida no cambia con la unidad: una luz
+    // fisica pide Falloff::InverseSquare (o Windowed) de forma explicita
+    // error si la unidad no tiene sentido para el tipo de luz
+    pub fn with_unit(mut self, unit: LightUnit) -> Result<Self, String> {
+        if unit != LightUnit::Relative && self.is_directional() {
+            return Err(format!("una luz direccional no se puede dar en {:?}", unit));
+        }
+        self.unit = unit;
+        Ok(self)
+    }
+
+    fn is_directional(&self) -> bool {
+        matches!(self.kind, LightKind::Directional { .. })
+    }
+
+    // angulo solido en el que se reparten los lumenes; una direccional no tiene
+    fn emission_solid_angle(&self) -> Option<f32> {
+        match self.kind {
+            // esfera completa
+            LightKind::Point | LightKind::SphereArea { .. } | LightKind::CubeArea { .. } => Some(4.0 * PI),
+            // casquete del cono
+            LightKind::Spot { cos_outer, .. } => Some(2.0 * PI * (1.0 - cos_outer).max(1e-4)),
+            // emisor lambertiano de un lado: I(theta) = I0 cos(theta)
+            LightKind::RectArea { .. } => Some(PI),
+            LightKind::Directional { .. } => None,
+        }
+    }
+
+    // pasar las luces fisicas a unidades de la escena con la exposicion de la camara
+    pub fn expose(&mut self, exposure: f32) {
+        let physical = match (self.unit, self.emission_solid_angle()) {
+            (LightUnit::Relative, _) => return,
+            (LightUnit::Candela, _) => self.intensity,
+            (LightUnit::Lumens, Some(solid_angle)) => self.intensity / solid_angle,
+            // with_unit no deja dar lumenes a una direccional
+            (LightUnit::Lumens, None) => return,
+        };
+        self.intensity = physical * exposure;
+        self.unit = LightUnit::Relative;
+    }
+
+    // muestras de la luz para un punto; el sombreado promedia su aporte
+    // se llama por cada punto sombreado: no reserva memoria, f recibe cada muestra
+    // y se devuelve cuantas hubo
+    pub fn samples(&self, point: Vector3, mut f: impl FnMut(LightSample)) -> usize {
+        match self.kind {
+            LightKind::Point => {
+                f(toward(point, self.position, self.falloff));
+                1
+            }
+            LightKind::Directional { direction, angular_radius } => {
+                if angular_radius <= 0.0 {
+                    f(LightSample { direction, distance: f32::INFINITY, weight: 1.0 });
+                    return 1;
+                }
+                disc_samples(point, direction, angular_radius, self.shadow_grid, |d| {
+                    f(LightSample { direction: (direction + d).normalized(), distance: f32::INFINITY, weight: 1.0 })
+                })
+            }
+            LightKind::Spot { direction, cos_inner, cos_outer } => {
+                let mut sample = toward(point, self.position, self.falloff);
+                let cos = (-sample.direction).dot(direction);
+                sample.weight *= if cos_inner > cos_outer {
+                    smoothstep(cos_outer, cos_inner, cos)
+                } else if cos >= cos_outer { 1.0 } else { 0.0 };
+                f(sample);
+                1
+            }
+            LightKind::RectArea { u_axis, v_axis } => {
+                // celdas del rectangulo con un punto al azar dentro de cada una
+                let normal = u_axis.cross(v_axis).normalized();
+                grid_samples(point, self.shadow_grid, |su, sv| {
+                    let mut sample = toward(point, self.position + u_axis * su + v_axis * sv, self.falloff);
+                    // cara trasera apagada; de lado se ve mas chica
+                    sample.weight *= (-sample.direction).dot(normal).max(0.0);
+                    f(sample);
+                })
+            }
+            LightKind::SphereArea { radius } => {
+                // disco que la esfera ocupa visto desde el punto
+                let to_center = self.position - point;
+                let dist = to_center.length();
+                if dist <= radius {
+                    f(toward(point, self.position, self.falloff));
+                    return 1;
+                }
+                let axis = to_center / dist;
+                disc_samples(point, axis, radius, self.shadow_grid, |d| f(toward(point, self.position + d, self.falloff)))
+            }
+            LightKind::CubeArea { half_size } => {
+                // solo las caras que miran al punto: un rayo hacia la cara de atras
+                // chocaria con el mismo bloque y la luz se taparia sola
+                let offset = point - self.position;
+                let mut faces = [(Vector3::zero(), Vector3::zero(), Vector3::zero(), 0.0f32); 3];
+                let mut count = 0;
+                for axis in 0..3 {
+                    let (n, t1, t2) = match axis {
+                        0 => (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+                        1 => (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0)),
+                        _ => (Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+                    };
+                    let along = offset.dot(n);
+                    if along.abs() > half_size {
+                        let n = n * along.signum();
+                        // cada cara pesa segun lo que se ve de ella
+                        let cos = (point - (self.position + n * half_size)).normalized().dot(n).max(0.0);
+                        faces[count] = (n, t1, t2, cos);
+                        count += 1;
+                    }
+                }
+                if count == 0 {
+                    f(toward(point, self.position, self.falloff));
+                    return 1;
+                }
+
+                // el total equivale a una luz puntual
+                let faces = &faces[..count];
+                let total: f32 = faces.iter().map(|face| face.3).sum();
+                let mut samples = 0;
+                for &(n, t1, t2, cos) in faces {
+                    let share = if total > 0.0 { cos * count as f32 / total } else { 1.0 };
+                    let face_center = self.position + n * half_size;
+                    samples += grid_samples(point, self.shadow_grid, |su, sv| {
+                        let on_light = face_center + t1 * (su * 2.0 * half_size) + t2 * (sv * 2.0 * half_size);
+                        let mut sample = toward(point, on_light, self.falloff);
+                        sample.weight *= share;
+                        f(sample);
+                    });
+                }
+                samples
+            }
+        }
+    }
+}
+
+// luces de un cuadro separadas una sola vez: primero las direccionales, despues las locales
+pub struct LightSet {
+    lights: Vec<Light>,
+    directional: usize,
+}
+
+impl LightSet {
+    pub fn new(mut lights: Vec<Light>) -> Self {
+        // orden estable: las locales quedan en el orden en que se agregaron
+        lights.sort_by_key(|l| !l.is_directional());
+        let directional = lights.iter().take_while(|l| l.is_directional()).count();
+        LightSet { lights, directional }
+    }
+
+    pub fn all(&self) -> &[Light] {
+        &self.lights
+    }
+
+    fn local(&self) -> &[Light] {
+        &self.lights[self.directional..]
+    }
+
+    // elegir que luces evaluar en un punto: las direccionales siempre; de las locales,
+    // si hay mas de max_local se sortean max_local con probabilidad proporcional a su
+    // aporte estimado (potencia * atenuacion) y cada una pesa 1 / (max_local * p) para
+    // que el promedio no cambie. Sin rayos de sombra: estimar es barato, sombrear no.
+    // Se llama una vez por punto sombreado: no reserva memoria, f recibe cada luz con su peso
+    pub fn select(&self, point: Vector3, max_local: usize, f: impl FnMut(&Light, f32)) {
+        self.select_with_offset(point, max_local, hash_point(point), f);
+    }
+
+    // offset en [0, 1) desplaza el sorteo estratificado
+    fn select_with_offset(&self, point: Vector3, max_local: usize, offset: f32, mut f: impl FnMut(&Light, f32)) {
+        for l in &self.lights[..self.directional] {
+            f(l, 1.0);
+        }
+        let local = self.local();
+        if local.len() <= max_local {
+            for l in local {
+                f(l, 1.0);
+            }
+            return;
+        }
+
+        // aporte estimado desde el centro de cada luz; nunca 0 para no perder luces
+        // que si llegan por el borde (areas grandes, ventanas de alcance)
+        let importance = |l: &Light| {
+            let dist = (l.position - point).length();
+            l.intensity * l.color.luminance() * l.falloff.attenuation(dist) + 1e-6
+        };
+        let total: f32 = local.iter().map(importance).sum();
+
+        // sorteo estratificado sobre la distribucion acumulada; la importancia de cada
+        // luz se vuelve a calcular al pasar en vez de guardarla
+        let mut cumulative = 0.0;
+        let mut index = 0;
+        let mut current = importance(&local[0]);
+        let mut pending: Option<(usize, f32)> = None;
+        for k in 0..max_local {
+            let target = (k as f32 + offset) / max_local as f32 * total;
+            while index + 1 < local.len() && cumulative + current < target {
+                cumulative += current;
+                index += 1;
+                current = importance(&local[index]);
+            }
+            let weight = total / (current * max_local as f32);
+            // la misma luz sorteada dos veces se evalua una sola vez con el peso sumado
+            match pending.as_mut() {
+                Some((last, w)) if *last == index => *w += weight,
+                _ => {
+                    if let Some((i, w)) = pending {
+                        f(&local[i], w);
+                    }
+                    pending = Some((index, weight));
+                }
+            }
+        }
+        if let Some((i, w)) = pending {
+            f(&local[i], w);
+        }
+    }
+}
+
+// muestra hacia un punto de la luz, atenuada segun la distancia
+fn toward(point: Vector3, target: Vector3, falloff: Falloff) -> LightSample {
+    let to_light = target - point;
+    let dist = to_light.length().max(1e-4);
+    LightSample { direction: to_light / dist, distance: dist, weight: falloff.attenuation(dist) }
+}
+
+// puntos estratificados en [-0.5, 0.5]^2: uno al azar dentro de cada celda de n x n
+// con n = 1 queda solo el centro (sombra dura); devuelve cuantos hubo
+fn grid_samples(point: Vector3, n: usize, mut f: impl FnMut(f32, f32)) -> usize {
+    let n = n.max(1);
+    if n == 1 {
+        f(0.0, 0.0);
+        return 1;
+    }
+    for i in 0..n {
+        for j in 0..n {
+            let cell = (i * n + j) as f32;
+            let su = (i as f32 + hash_point(point + Vector3::new(cell, 0.0, 0.0))) / n as f32 - 0.5;
+            let sv = (j as f32 + hash_point(point + Vector3::new(0.0, cell, 0.0))) / n as f32 - 0.5;
+            f(su, sv);
+        }
+    }
+    n * n
+}
+
+// desplazamientos dentro de un disco de radio dado perpendicular a axis; devuelve cuantos hubo
+fn disc_samples(point: Vector3, axis: Vector3, radius: f32, n: usize, mut f: impl FnMut(Vector3)) -> usize {
+    let n = n.max(1);
+    if n == 1 {
+        f(Vector3::zero());
+        return 1;
     }
-}
\ No newline at end of file
+    // base ortonormal alrededor del eje
+    let helper = if axis.y.abs() < 0.9 { Vector3::new(0.0, 1.0, 0.0) } else { Vector3::new(1.0, 0.0, 0.0) };
+    let tangent = axis.cross(helper).normalized();
+    let bitangent = axis.cross(tangent);
+
+    // rotacion distinta por punto: la penumbra queda con ruido en vez de escalones
+    let rotation = hash_point(point) * std::f32::consts::TAU;
+    for i in 0..n {
+        for j in 0..n {
+            // celda (i, j) del disco en coordenadas polares
+            let r = radius * ((i as f32 + 0.5) / n as f32).sqrt();
+            let phi = rotation + std::f32::consts::TAU * (j as f32 + 0.5) / n as f32 + i as f32 * 0.7;
+            f(tangent * (r * phi.cos()) + bitangent * (r * phi.sin()));
+        }
+    }
+    n * n
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// numero pseudoaleatorio en [0, 1) estable para un punto
+pub(crate) fn hash_point(p: Vector3) -> f32 {
+    let h = (p.x * 12.9898 + p.y * 78.233 + p.z * 37.719).sin() * 43758.547;
+    h - h.floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_set_puts_directional_lights_first() {
+        let set = LightSet::new(vec![
+            Light::new(Vector3::new(1.0, 0.0, 0.0), Color::white(), 1.0),
+            Light::directional(Vector3::new(0.0, 1.0, 0.0), Color::white(), 1.0, 0.0),
+            Light::new(Vector3::new(2.0, 0.0, 0.0), Color::white(), 1.0),
+        ]);
+        assert!(set.all()[0].is_directional());
+        assert_eq!(set.local().len(), 2);
+        assert_eq!(set.local()[0].position.x, 1.0);
+    }
+
+    #[test]
+    fn weighted_selection_averages_to_the_full_sum() {
+        // trece luces locales de potencias distintas y una direccional; se sortean 4
+        let mut lights: Vec<Light> = (0..12)
+            .map(|i| {
+                let intensity = 0.2 + (i % 5) as f32 * 0.7;
+                let color = Color::new(1.0 - (i % 4) as f32 * 0.3, 0.5, 0.2 + (i % 3) as f32 * 0.4);
+                Light::new(Vector3::new(i as f32 * 1.5 - 8.0, 3.0, (i % 3) as f32), color, intensity)
+            })
+            .collect();
+        // una luz fuerte y cercana sale sorteada varias veces en el mismo punto
+        lights.push(Light::new(Vector3::new(0.0, 1.0, -1.0), Color::new(0.4, 0.9, 1.0), 12.0));
+        lights.push(Light::directional(Vector3::new(0.3, 1.0, 0.2), Color::white(), 1.0, 0.0));
+        let set = LightSet::new(lights);
+        let point = Vector3::new(0.5, 0.0, -1.0);
+
+        // canal rojo del aporte de cada luz sin sombras; el sorteo usa la luminancia,
+        // asi que no es proporcional a la importancia y el promedio no sale exacto de casualidad
+        let contribution = |l: &Light| match l.kind {
+            LightKind::Directional { .. } => l.intensity * l.color.r,
+            _ => l.intensity * l.color.r * l.falloff.attenuation((l.position - point).length()),
+        };
+        let full: f32 = set.all().iter().map(contribution).sum();
+
+        let seeds = 2000;
+        let mut average = 0.0;
+        for seed in 0..seeds {
+            let offset = (seed as f32 + 0.5) / seeds as f32;
+            let mut picked = 0;
+            set.select_with_offset(point, 4, offset, |l, w| {
+                average += contribution(l) * w / seeds as f32;
+                picked += 1;
+            });
+            // la direccional siempre, y a lo sumo 4 locales
+            assert!((2..=5).contains(&picked));
+        }
+        assert!((average - full).abs() < full * 0.01, "promedio {} contra {}", average, full);
+    }
+
+    #[test]
+    fn physical_units_follow_the_light_kind() {
+        let sun = || Light::directional(Vector3::new(0.0, 1.0, 0.0), Color::white(), 1.0, 0.0);
+        assert!(sun().with_unit(LightUnit::Lumens).is_err());
+        assert!(sun().with_unit(LightUnit::Candela).is_err());
+        assert!(sun().with_unit(LightUnit::Relative).is_ok());
+
+        // 4 pi lm en una esfera = 1 cd
+        let mut bulb = Light::new(Vector3::zero(), Color::white(), 4.0 * PI).with_unit(LightUnit::Lumens).unwrap();
+        bulb.expose(0.5);
+        assert!((bulb.intensity - 0.5).abs() < 1e-5);
+        let mut lamp = Light::sphere_area(Vector3::zero(), 0.1, Color::white(), 80.0).with_unit(LightUnit::Candela).unwrap();
+        lamp.expose(0.5);
+        assert!((lamp.intensity - 40.0).abs() < 1e-4);
+        // ya expuesta: otra llamada no la vuelve a escalar
+        lamp.expose(0.5);
+        assert!((lamp.intensity - 40.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn cube_light_samples_only_the_faces_it_shows() {
+        let light = Light::cube_area(Vector3::zero(), 1.0, Color::white(), 1.0);
+        // en diagonal se ven tres caras, de frente solo una
+        let mut weight = 0.0;
+        assert_eq!(light.samples(Vector3::new(3.0, 3.0, 3.0), |s| weight += s.weight), 3 * SOFT_SHADOW_GRID * SOFT_SHADOW_GRID);
+        assert!(weight > 0.0);
+        let mut toward_light = true;
+        let count = light.samples(Vector3::new(4.0, 0.0, 0.0), |s| toward_light &= s.direction.x < 0.0 && s.distance >= 3.5);
+        assert_eq!(count, SOFT_SHADOW_GRID * SOFT_SHADOW_GRID);
+        assert!(toward_light);
+    }
+}
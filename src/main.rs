@@ -8,21 +8,73 @@ mod color;
 mod skybox;
 mod texture;
 mod mesh;
+mod vox;
+mod nbt;
+mod schematic;
+mod export;
+mod ibl;
+mod sky;
+mod blocklight;
+mod probes;
+mod photon;
+#[cfg(test)]
+mod fixture;
 
 use raylib::prelude::*;
 use rayon::prelude::*;
 use camera::Camera;
-use ray::Ray as CustomRay;
+use ray::{Ray as CustomRay, RayDifferential};
 use cube::Cube;
-use light::Light;
+use light::{hash_point, Falloff, Light, LightSample, LightSet, LightUnit, SOFT_SHADOW_GRID};
 use color::Color as CustomColor;
-use material::Material;
-use skybox::Skybox;
+use material::{AlphaMode, Material};
+use skybox::{SkyOrientation, Skybox};
 use texture::TextureManager;
 use mesh::Mesh;
+use blocklight::LightGrid;
+use probes::ProbeGrid;
+use photon::{CausticCache, CausticMap, Photon};
 
 const WIDTH: i32 = 640;
 const HEIGHT: i32 = 480;
+// angulo que cubre un pixel (la camara usa distancia focal 1 y v en [-1, 1])
+const PIXEL_ANGLE: f32 = 2.0 / HEIGHT as f32;
+// duracion de un dia en segundos (el sol gira a 0.2 rad/s)
+const DAY_LENGTH: f32 = std::f32::consts::TAU / 0.2;
+// dias por ciclo de fases de la luna (acelerado para que se note)
+const MOON_CYCLE_DAYS: f32 = 8.0;
+// luz de luna con luna llena y alta; el radio angular suaviza sus sombras
+const MOON_INTENSITY: f32 = 0.4;
+const MOON_ANGULAR_RADIUS: f32 = 0.04;
+// sol direccional sin atenuacion, con el sol alto y el cielo limpio
+const SUN_INTENSITY: f32 = 1.0;
+// radio angular del disco solar (el mismo que dibuja el cielo): penumbra angosta
+const SUN_ANGULAR_RADIUS: f32 = 0.02;
+// maximo de muestras de sombra por lado que se puede elegir con L
+const MAX_SHADOW_GRID: usize = 4;
+// luces locales sombreadas por punto; con mas luces (una aldea con antorchas) se sortean
+const LIGHTS_PER_POINT: usize = 8;
+// luz indirecta de un bloque emisivo pegado a la superficie
+const BLOCK_LIGHT_STRENGTH: f32 = 0.6;
+// oclusion ambiental trazada: rayos por punto y hasta donde cuenta un obstaculo
+const AO_RAYS: usize = 8;
+const AO_DISTANCE: f32 = 1.0;
+// sondas de irradiancia: separacion, cuantas se actualizan por cuadro y a que
+// profundidad de rebote se sombrean sus rayos (sin reflejos encima)
+const PROBE_SPACING: f32 = 1.5;
+const PROBES_PER_FRAME: usize = 16;
+const PROBE_DEPTH: u32 = 2;
+// rebotes de reflejo y refraccion; pasado este el rayo devuelve negro
+const MAX_DEPTH: u32 = 2;
+// causticas: fotones por cada bloque de agua o vidrio y luz, radio de busqueda y
+// cuantas veces puede cruzar medios un foton
+const PHOTONS_PER_CASTER: usize = 400;
+const CAUSTIC_RADIUS: f32 = 0.2;
+const MAX_PHOTON_BOUNCES: usize = 4;
+// tamaño de cada voxel de los modelos externos y donde se apoyan si no se indican:
+// delante del diorama, fuera del suelo
+const MODEL_VOXEL_SIZE: f32 = 0.25;
+const MODEL_POSITION: Vector3 = Vector3 { x: 0.0, y: 0.0, z: 8.0 };
 
 fn main() {
     let (mut rl, thread) = raylib::init()
@@ -39,22 +91,41 @@ fn main() {
     );
 
     let texture_manager = TextureManager::new();
-    let cubes = create_scene();
+    // modelos externos: cargo run --release -- modelo.vox@x,y,z casa.schem
+    let models: Vec<String> = std::env::args().skip(1).collect();
+    let cubes = create_scene(&models);
     let meshes = load_meshes();
     
-    let mut sun = Light::new(
-        Vector3::new(5.0, 10.0, 5.0),
-        CustomColor::new(1.0, 1.0, 0.9),
-        2.5
-    );
-
-    let skybox = Skybox::new(&mut rl, &thread);
+    let mut skybox = Skybox::new(&mut rl, &thread);
+    // entorno de esta escena: (mapa lat-long, rotacion en radianes, intensidad)
+    let environment = ("assets/skybox/environment.hdr", 0.0, 1.0);
+    if std::path::Path::new(environment.0).exists() {
+        skybox.set_environment(environment.0, environment.1, environment.2);
+    }
+    // el cielo gira con el diorama y las nubes del cubemap avanzan despacio
+    skybox.orientation = SkyOrientation::World;
+    skybox.spin = 0.01;
 
     let mut time: f32 = 0.0;
     let mut world_angle: f32 = 0.0; // rotación del diorama
     let mut camera_angle: f32 = 0.0;
     let mut camera_pitch: f32 = 0.2; // inclinación hacia abajo
     let mut camera_distance: f32 = 10.0;
+    // rayos de sombra por lado en luces con tamaño: calidad contra velocidad
+    let mut shadow_grid = SOFT_SHADOW_GRID;
+    // luz por bloques como luz indirecta; se recalcula solo si cambian los bloques
+    let mut light_grid = LightGrid::build(&cubes);
+    let mut use_block_light = true;
+    let mut ao_mode = AoMode::Voxel;
+    // luz rebotada desde la grilla de sondas; mientras esta activa reemplaza a la luz
+    // por bloques (las sondas ya ven las paredes y los emisivos), por eso arranca apagada
+    let mut probe_grid = ProbeGrid::build(&cubes, PROBE_SPACING);
+    let mut use_probes = false;
+    let mut ao_debug = false;
+    // causticas del agua y el vidrio con mapa de fotones (se rehace solo si cambian
+    // las luces o los bloques)
+    let mut use_caustics = true;
+    let mut caustics = CausticCache::new(CAUSTIC_RADIUS);
 
     let mut image = Image::gen_image_color(WIDTH, HEIGHT, Color::BLACK);
 
@@ -65,6 +136,14 @@ fn main() {
     println!("  Q/E - Acercar/Alejar");
     println!("  CLIC DERECHO - Orbitar cámara");
     println!("  RUEDA MOUSE - Zoom");
+    println!("  K - Cielo procedural / cubemap");
+    println!("  O - Orientacion del cielo (fija / mundo / sol)");
+    println!("  B - Luz por bloques (luz indirecta)");
+    println!("  P - Sondas de irradiancia (luz rebotada)");
+    println!("  M - Oclusion ambiental (voxel / rayos / no)");
+    println!("  V - Ver solo la oclusion ambiental");
+    println!("  C - Causticas (mapa de fotones)");
+    println!("  X - Exportar escena (OBJ + glTF)");
     println!("  ESC - Salir");
 
     while !rl.window_should_close() {
@@ -99,6 +178,73 @@ fn main() {
             camera_angle -= md.x * sensitivity;
             camera_pitch = (camera_pitch - md.y * sensitivity).clamp(-1.2, 1.2);
         }
+        if rl.is_key_pressed(KeyboardKey::KEY_K) {
+            skybox.procedural = !skybox.procedural;
+            skybox.update_lighting();
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_O) {
+            skybox.orientation = match skybox.orientation {
+                SkyOrientation::Fixed => SkyOrientation::World,
+                SkyOrientation::World => SkyOrientation::SunPath,
+                SkyOrientation::SunPath => SkyOrientation::Fixed,
+            };
+            println!("Orientacion del cielo: {:?}", skybox.orientation);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_L) {
+            shadow_grid = shadow_grid % MAX_SHADOW_GRID + 1;
+            println!("Muestras de sombra: {}x{}", shadow_grid, shadow_grid);
+        }
+        // compensacion de exposicion en medios pasos
+        if rl.is_key_pressed(KeyboardKey::KEY_EQUAL) {
+            camera.exposure_compensation += 0.5;
+            println!("Exposicion: {:+.1} EV", camera.exposure_compensation);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_MINUS) {
+            camera.exposure_compensation -= 0.5;
+            println!("Exposicion: {:+.1} EV", camera.exposure_compensation);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_B) {
+            use_block_light = !use_block_light;
+            println!("Luz por bloques: {}", if use_block_light { "si" } else { "no" });
+            if use_probes {
+                println!("  (sin efecto mientras las sondas esten activas; P las apaga)");
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            use_probes = !use_probes;
+            println!("Sondas de irradiancia: {}", if use_probes { "si" } else { "no" });
+            if use_probes && use_block_light {
+                println!("  (reemplazan a la luz por bloques: B no tiene efecto hasta apagarlas)");
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_M) {
+            ao_mode = match ao_mode {
+                AoMode::Off => AoMode::Voxel,
+                AoMode::Voxel => AoMode::RayTraced,
+                AoMode::RayTraced => AoMode::Off,
+            };
+            println!("Oclusion ambiental: {:?}", ao_mode);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_V) {
+            ao_debug = !ao_debug;
+            println!("Ver solo oclusion ambiental: {}", if ao_debug { "si" } else { "no" });
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_C) {
+            use_caustics = !use_caustics;
+            println!("Causticas: {}", if use_caustics { "si" } else { "no" });
+        }
+        // exportar la geometria para revisarla en otros renderers
+        if rl.is_key_pressed(KeyboardKey::KEY_X) {
+            match export::export_obj("export/diorama.obj", &cubes, &meshes, &texture_manager) {
+                Ok(()) => println!("Escena exportada a export/diorama.obj"),
+                Err(e) => eprintln!("No se pudo exportar OBJ: {}", e),
+            }
+            match export::export_gltf("export/diorama.gltf", &cubes, &meshes, &texture_manager) {
+                Ok(()) => println!("Escena exportada a export/diorama.gltf"),
+                Err(e) => eprintln!("No se pudo exportar glTF: {}", e),
+            }
+        }
+
         let wheel = rl.get_mouse_wheel_move();
         if wheel != 0.0 { camera_distance -= wheel * 1.0; }
 
@@ -112,35 +258,120 @@ fn main() {
         camera.position = target + Vector3::new(x, y, z);
         camera.look_at = target;
 
+        // el sol da la vuelta completa: de noche queda bajo el horizonte
         let day_progress = (time * 0.2).sin();
-        sun.position = Vector3::new(
-            15.0 * (time * 0.2).cos(),
-            15.0 * day_progress.abs(),
-            15.0 * (time * 0.2).sin()
-        );
-        
-        sun.color = if day_progress > 0.3 {
-            CustomColor::new(1.0, 1.0, 0.9)
-        } else if day_progress > -0.3 {
-            CustomColor::new(1.0, 0.6, 0.3)
-        } else {
-            CustomColor::new(0.2, 0.2, 0.4)
-        };
-
-        sun.intensity = (day_progress.max(0.3) * 2.5).max(0.8);
-
-        // luces: sol + luces de relleno + cubos emisivos
-        let mut lights: Vec<Light> = vec![Light::new(sun.position, sun.color, sun.intensity)];
-        lights.push(Light::new(Vector3::new(-5.0, 8.0, 0.0), CustomColor::new(0.8, 0.9, 1.0), 1.2));
-        lights.push(Light::new(Vector3::new(5.0, 6.0, -5.0), CustomColor::new(1.0, 0.95, 0.85), 1.0));
+        let sun_dir = Vector3::new((time * 0.2).cos(), day_progress, (time * 0.2).sin()).normalized();
+        skybox.set_sun_direction(sun_dir);
+        skybox.update_orientation(world_angle, time);
+
+        // color e intensidad del sol segun cuanta atmosfera atraviesa
+        let (sun_color, sun_strength) = skybox.sun_light();
+
+        // la luna va opuesta al sol y empieza llena
+        skybox.set_time(time);
+        skybox.set_moon_phase(0.5 + time / DAY_LENGTH / MOON_CYCLE_DAYS);
+        let (moon_color, moon_strength) = skybox.moon_light();
+
+        // luces: sol o luna + luces de la escena + cubos emisivos
+        // todas en el espacio del diorama (giran con el); el sol y la luna se llevan a ese espacio
+        let mut lights: Vec<Light> = Vec::new();
+        if sun_strength > 0.0 {
+            lights.push(Light::directional(
+                rotate_vec3_y(sun_dir, -world_angle),
+                sun_color,
+                SUN_INTENSITY * sun_strength,
+                SUN_ANGULAR_RADIUS,
+            ));
+        }
+        if moon_strength > 0.0 {
+            lights.push(Light::directional(
+                rotate_vec3_y(skybox.moon_direction(), -world_angle),
+                moon_color,
+                MOON_INTENSITY * moon_strength,
+                MOON_ANGULAR_RADIUS,
+            ));
+        }
+        // panel de relleno sobre la escena, mirando hacia abajo; parejo, no cae con la distancia
+        lights.push(Light::rect_area(
+            Vector3::new(-5.0, 8.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 2.0),
+            CustomColor::from_srgb(0.8, 0.9, 1.0),
+            0.25,
+        ).with_falloff(Falloff::None));
+        lights.push(Light::new(Vector3::new(5.0, 6.0, -5.0), CustomColor::from_srgb(1.0, 0.95, 0.85), 1.0));
+        // luces en unidades fisicas (la exposicion de la camara las convierte):
+        // farol colgado al frente, bombilla esferica de 90 cd con sombras con penumbra,
+        // y foco de 400 lm sobre la puerta de la casa que se apaga a los 10 bloques
+        let physical = [
+            Light::sphere_area(Vector3::new(3.0, 2.5, 2.0), 0.15, CustomColor::from_srgb(1.0, 0.8, 0.5), 90.0)
+                .with_falloff(Falloff::InverseSquare)
+                .with_unit(LightUnit::Candela),
+            Light::spot(
+                Vector3::new(0.0, 4.0, -5.0),
+                Vector3::new(0.0, 0.0, -2.0),
+                CustomColor::from_srgb(1.0, 0.85, 0.6),
+                400.0,
+                0.3,
+                0.5,
+            ).with_falloff(Falloff::Windowed { range: 10.0 }).with_unit(LightUnit::Lumens),
+        ];
+        for light in physical {
+            match light {
+                Ok(light) => lights.push(light),
+                Err(e) => eprintln!("Luz descartada: {}", e),
+            }
+        }
+        // cubos emisivos (la fogata): la luz sale de toda su superficie
         for cube in &cubes {
             if let Some(em) = cube.material.emission {
                 let inten = (em.r + em.g + em.b) / 3.0;
-                lights.push(Light::new(cube.center, em, inten.max(1.5)));
+                lights.push(Light::cube_area(cube.center, cube.size, em, inten.max(1.5)));
             }
         }
+        for l in &mut lights {
+            l.shadow_grid = shadow_grid;
+            l.expose(camera.physical_exposure());
+        }
+        let lights = LightSet::new(lights);
 
-        render_threaded(&camera, &cubes, &meshes, &lights, &skybox, &texture_manager, &mut image, time, world_angle);
+        if light_grid.refresh(&cubes) {
+            println!("Luz por bloques recalculada");
+        }
+        // la luz que cruza el agua y el vidrio llega como fotones (concentrada por la refraccion);
+        // las sondas y los rayos de la camara usan el mismo mapa para no contar distinto esa luz
+        if use_caustics {
+            caustics.refresh(lights.all(), &cubes, || shoot_photons(lights.all(), &cubes, &meshes, &texture_manager, time));
+        } else {
+            caustics.clear();
+        }
+        // unas pocas sondas por cuadro siguen al sol; sus rayos ya ven la luz rebotada
+        // de las demas sondas, asi los rebotes se acumulan con los cuadros
+        if use_probes {
+            let indirect = Indirect { grid: &light_grid, probes: Some(&probe_grid), caustics: caustics.map(), block_light: use_block_light, ao: ao_mode, ao_debug: false, probe_ray: true };
+            let updates = probe_grid.trace_batch(PROBES_PER_FRAME, |origin, dir| {
+                let distance = hit_distance(&CustomRay::new(origin, dir), &cubes, &meshes, &texture_manager, time);
+                let world_dir = rotate_vec3_y(dir, world_angle);
+                if distance.is_infinite() {
+                    return (skybox.ambient_color(&world_dir), distance);
+                }
+                let ray = CustomRay::new(rotate_vec3_y(origin, world_angle), world_dir);
+                let radiance = cast_ray(&ray, &cubes, &meshes, &lights, &skybox, &indirect, &texture_manager, PROBE_DEPTH, time, world_angle);
+                (radiance, distance)
+            });
+            probe_grid.apply(updates);
+        }
+        let indirect = Indirect {
+            grid: &light_grid,
+            probes: use_probes.then_some(&probe_grid),
+            caustics: caustics.map(),
+            block_light: use_block_light,
+            ao: ao_mode,
+            ao_debug,
+            probe_ray: false,
+        };
+
+        render_threaded(&camera, &cubes, &meshes, &lights, &skybox, &indirect, &texture_manager, &mut image, time, world_angle);
 
         let texture = rl.load_texture_from_image(&thread, &image).unwrap();
         
@@ -157,8 +388,9 @@ fn render_threaded(
     camera: &Camera,
     cubes: &Vec<Cube>,
     meshes: &Vec<Mesh>,
-    lights: &Vec<Light>,
+    lights: &LightSet,
     skybox: &Skybox,
+    indirect: &Indirect,
     textures: &TextureManager,
     image: &mut Image,
     time: f32,
@@ -177,12 +409,17 @@ fn render_threaded(
                     let u = (x as f32 / WIDTH as f32) * 2.0 - 1.0;
                     let v = -((y as f32 / HEIGHT as f32) * 2.0 - 1.0);
 
-                    let ray = camera.get_ray(u * aspect_ratio, v);
-                        let color = cast_ray(&ray, cubes, meshes, lights, skybox, textures, 0, time, world_angle);
+                    // un pixel a la derecha y uno abajo, para los rayos diferenciales
+                    let du = 2.0 / WIDTH as f32 * aspect_ratio;
+                    let dv = -2.0 / HEIGHT as f32;
+                    let ray = camera.get_ray(u * aspect_ratio, v, du, dv);
+                        let color = cast_ray(&ray, cubes, meshes, lights, skybox, indirect, textures, 0, time, world_angle);
                     
-                    let r = (color.r.clamp(0.0, 1.0) * 255.0) as u8;
-                    let g = (color.g.clamp(0.0, 1.0) * 255.0) as u8;
-                    let b = (color.b.clamp(0.0, 1.0) * 255.0) as u8;
+                    // la iluminacion es lineal; la pantalla espera sRGB
+                    let color = (color * camera.exposure_gain()).to_srgb();
+                    let r = (color.r * 255.0 + 0.5) as u8;
+                    let g = (color.g * 255.0 + 0.5) as u8;
+                    let b = (color.b * 255.0 + 0.5) as u8;
                     
                     (x, y, Color::new(r, g, b, 255))
                 })
@@ -199,16 +436,18 @@ fn cast_ray(
     ray: &CustomRay,
     cubes: &Vec<Cube>,
         meshes: &Vec<Mesh>,
-    lights: &Vec<Light>,
+    lights: &LightSet,
     skybox: &Skybox,
+    indirect: &Indirect,
     textures: &TextureManager,
     depth: u32,
     time: f32,
     world_angle: f32,
 ) -> CustomColor {
-    if depth > 2 {
+    if depth > MAX_DEPTH {
         return CustomColor::new(0.0, 0.0, 0.0);
     }
+    let bounces = !indirect.probe_ray;
 
     // rotar rayo al espacio del objeto
     let rr = rotate_ray_y(ray, -world_angle);
@@ -218,7 +457,7 @@ fn cast_ray(
     let mut hit_mesh: Option<(&Mesh, f32, f32, Vector3)> = None;
 
     for cube in cubes {
-        if let Some((t, u, v)) = cube.intersect_with_uv(&rr) {
+        if let Some((t, u, v)) = intersect_cube(cube, &rr, textures, time) {
             if t < closest_t && t > 0.001 {
                 closest_t = t;
                 hit_cube = Some((cube, u, v));
@@ -241,12 +480,33 @@ fn cast_ray(
     if let Some((cube, u, v)) = hit_cube {
         // calcular punto de impacto y normal
         let hit_point_obj = rr.at(closest_t);
-        let normal_obj = cube.normal_at(hit_point_obj);
+        let face_normal = cube.normal_at(hit_point_obj);
+        // cutout: por los huecos se ve la cara de atras, se ilumina del lado que mira al rayo
+        let normal_obj = if matches!(cube.material.alpha_mode, AlphaMode::Cutout(_)) && face_normal.dot(rr.direction) > 0.0 {
+            -face_normal
+        } else {
+            face_normal
+        };
         let hit_point = rotate_vec3_y(hit_point_obj, world_angle);
         let normal = rotate_vec3_y(normal_obj, world_angle).normalized();
         
-        let texture_color = if let Some(ref tex_name) = cube.material.texture_name {
-            textures.get_color(tex_name, u, v, time, cube.material.animated)
+        // tamaño del pixel en UV para elegir el mipmap, desde los rayos diferenciales
+        let footprint = match ray.differential.and_then(|d| d.hit_offsets(hit_point, normal)) {
+            Some((dpdx, dpdy)) => {
+                let (dudx, dvdx) = cube.uv_derivatives(normal_obj, rotate_vec3_y(dpdx, -world_angle));
+                let (dudy, dvdy) = cube.uv_derivatives(normal_obj, rotate_vec3_y(dpdy, -world_angle));
+                dudx.hypot(dvdx).max(dudy.hypot(dvdy))
+            }
+            None => {
+                // sin diferenciales: estimar con la distancia (la cara del cubo mide cube.size)
+                let cos_theta = ray.direction.dot(normal).abs().max(0.1);
+                closest_t * PIXEL_ANGLE / cos_theta / cube.size
+            }
+        };
+
+        let face_texture = cube.material.texture_for_face(face_normal, textures);
+        let texture_color = if let Some(tex_name) = face_texture {
+            textures.sample(tex_name, u, v, footprint, time, &cube.material)
         } else {
             CustomColor::white()
         };
@@ -259,47 +519,32 @@ fn cast_ray(
         }
 
         let view_dir = (ray.origin - hit_point).normalized();
-        let mut color = base_color * 0.35; // ambiental 
-
-        // luz
-        for l in lights {
-            let to_light = l.position - hit_point;
-            let dist = to_light.length();
-            let light_dir = to_light / dist;
-
-            // verificar sombras
-            let shadow_ray = CustomRay::new(hit_point + normal * 0.002, light_dir);
-            let mut in_shadow = false;
-            for oc in cubes {
-                if let Some((t, _, _)) = oc.intersect_with_uv(&shadow_ray) {
-                    if t > 0.001 && t < dist - 0.001 {
-                        in_shadow = true;
-                        break;
-                    }
-                }
-            }
-            if in_shadow { continue; }
-
-            let ndotl = normal.dot(light_dir).max(0.0);
-            let half_dir = (light_dir + view_dir).normalized();
-            let specular = normal.dot(half_dir).max(0.0).powf(32.0) * cube.material.specular;
-
-            // Distancia
-            let attenuation = 1.0 / (1.0 + 0.15 * dist + 0.05 * dist * dist);
-            let contrib = (base_color * ndotl + CustomColor::white() * specular) * l.intensity * attenuation;
-            color = color + contrib * l.color;
+        let ao = ambient_occlusion(indirect, hit_point_obj, normal_obj, cubes, meshes, textures, time);
+        if indirect.ao_debug {
+            return CustomColor::white() * ao;
+        }
+        let irradiance = diffuse_irradiance(indirect, skybox, hit_point_obj, normal_obj, normal);
+        let mut color = ambient_light(skybox, &cube.material, base_color, irradiance, normal, view_dir);
+        color = block_light(indirect, color, base_color, hit_point_obj, normal_obj) * ao;
+
+        // luz directa en el espacio del diorama, igual que los objetos que tapan
+        let view_obj = rotate_vec3_y(view_dir, -world_angle);
+        color = color + direct_light(&cube.material, base_color, hit_point_obj, normal_obj, view_obj, cube.receives_shadows, lights, cubes, meshes, textures, time, indirect.caustics.is_some());
+        if let Some(map) = indirect.caustics {
+            color = color + base_color * map.gather(hit_point_obj, normal_obj);
         }
 
         // refleccion
-        if cube.material.reflectivity > 0.0 {
-            let reflect_dir = ray.direction - normal * 2.0 * ray.direction.dot(normal);
-            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir);
-            let reflect_color = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, textures, depth + 1, time, world_angle);
+        if bounces && cube.material.reflectivity > 0.0 {
+            let reflect_dir = reflect(ray.direction, normal);
+            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir)
+                .with_differential(bounce_differential(ray, hit_point, normal, |d| Some(reflect(d, normal))));
+            let reflect_color = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, indirect, textures, depth + 1, time, world_angle);
             color = color * (1.0 - cube.material.reflectivity) + reflect_color * cube.material.reflectivity;
         }
 
         // refracción
-        if cube.material.transparency > 0.0 {
+        if bounces && cube.material.transparency > 0.0 {
             let mut n = normal;
             let mut eta = 1.0 / cube.material.refractive_index.max(1e-3);
             let cosi = (-ray.direction).dot(n).clamp(-1.0, 1.0);
@@ -312,17 +557,19 @@ fn cast_ray(
 
             let k = 1.0 - eta * eta * (1.0 - cosi_abs * cosi_abs);
             let mut refract_color = CustomColor::black();
-            let reflect_dir = ray.direction - normal * 2.0 * ray.direction.dot(normal);
-            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir);
-            let reflect_col = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, textures, depth + 1, time, world_angle);
+            let reflect_dir = reflect(ray.direction, normal);
+            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir)
+                .with_differential(bounce_differential(ray, hit_point, normal, |d| Some(reflect(d, normal))));
+            let reflect_col = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, indirect, textures, depth + 1, time, world_angle);
 
             let r0 = ((1.0 - cube.material.refractive_index) / (1.0 + cube.material.refractive_index)).powi(2);
             let fresnel = r0 + (1.0 - r0) * (1.0 - cosi_abs).powi(5);
 
             if k >= 0.0 {
                 let refract_dir = ray.direction * eta + n * (eta * cosi_abs - k.sqrt());
-                let refr_ray = CustomRay::new(hit_point - n * 0.001, refract_dir.normalized());
-                refract_color = cast_ray(&refr_ray, cubes, meshes, lights, skybox, textures, depth + 1, time, world_angle);
+                let refr_ray = CustomRay::new(hit_point - n * 0.001, refract_dir.normalized())
+                    .with_differential(bounce_differential(ray, hit_point, normal, |d| refract(d, n, eta)));
+                refract_color = cast_ray(&refr_ray, cubes, meshes, lights, skybox, indirect, textures, depth + 1, time, world_angle);
                 color = color * (1.0 - cube.material.transparency)
                     + (reflect_col * fresnel + refract_color * (1.0 - fresnel)) * cube.material.transparency;
             } else {
@@ -331,14 +578,34 @@ fn cast_ray(
             }
         }
 
+        // alpha blending: lo que se ve a traves del texel, sin contar como rebote
+        if cube.material.alpha_mode == AlphaMode::Blend {
+            let alpha = face_texture.map(|name| textures.alpha(name, u, v, time, &cube.material)).unwrap_or(1.0);
+            if alpha < 1.0 {
+                let through_ray = CustomRay::new(hit_point + ray.direction * 0.001, ray.direction)
+                    .with_differential(ray.differential);
+                let behind = cast_ray(&through_ray, cubes, meshes, lights, skybox, indirect, textures, depth, time, world_angle);
+                color = color * alpha + behind * (1.0 - alpha);
+            }
+        }
+
         color
     } else if let Some((mesh, u, v, n_obj)) = hit_mesh {
         let hit_point_obj = rr.at(closest_t);
         let normal = rotate_vec3_y(n_obj, world_angle).normalized();
         let hit_point = rotate_vec3_y(hit_point_obj, world_angle);
 
+        // aproximacion: se asume que las UVs del modelo cubren ~1 unidad de espacio local
+        let footprint = match ray.differential.and_then(|d| d.hit_offsets(hit_point, normal)) {
+            Some((dpdx, dpdy)) => dpdx.length().max(dpdy.length()) / mesh.scale,
+            None => {
+                let cos_theta = ray.direction.dot(normal).abs().max(0.1);
+                closest_t * PIXEL_ANGLE / cos_theta / mesh.scale
+            }
+        };
+
         let texture_color = if let Some(ref tex_name) = mesh.material.texture_name {
-            textures.get_color(tex_name, u, v, time, mesh.material.animated)
+            textures.sample(tex_name, u, v, footprint, time, &mesh.material)
         } else {
             CustomColor::white()
         };
@@ -349,44 +616,30 @@ fn cast_ray(
         }
 
         let view_dir = (ray.origin - hit_point).normalized();
-        let mut color = base_color * 0.35; // ambiental 
-        for l in lights {
-            let to_light = l.position - hit_point;
-            let dist = to_light.length();
-            let light_dir = to_light / dist;
-            let shadow_ray = CustomRay::new(hit_point + normal * 0.002, light_dir);
-            let mut in_shadow = false;
-            for oc in cubes {
-                if let Some((t, _, _)) = oc.intersect_with_uv(&shadow_ray) {
-                    if t > 0.001 && t < dist - 0.001 { in_shadow = true; break; }
-                }
-            }
-            if !in_shadow {
-                for om in meshes {
-                    if let Some((t, _, _, _)) = om.intersect_with_uv_normal(&shadow_ray) {
-                        if t > 0.001 && t < dist - 0.001 { in_shadow = true; break; }
-                    }
-                }
-            }
-            if in_shadow { continue; }
-            let ndotl = normal.dot(light_dir).max(0.0);
-            let half_dir = (light_dir + view_dir).normalized();
-            let specular = normal.dot(half_dir).max(0.0).powf(32.0) * mesh.material.specular;
-            let attenuation = 1.0 / (1.0 + 0.15 * dist + 0.05 * dist * dist);
-            let contrib = (base_color * ndotl + CustomColor::white() * specular) * l.intensity * attenuation;
-            color = color + contrib * l.color;
+        let ao = ambient_occlusion(indirect, hit_point_obj, n_obj, cubes, meshes, textures, time);
+        if indirect.ao_debug {
+            return CustomColor::white() * ao;
+        }
+        let irradiance = diffuse_irradiance(indirect, skybox, hit_point_obj, n_obj, normal);
+        let mut color = ambient_light(skybox, &mesh.material, base_color, irradiance, normal, view_dir);
+        color = block_light(indirect, color, base_color, hit_point_obj, n_obj) * ao;
+        let view_obj = rotate_vec3_y(view_dir, -world_angle);
+        color = color + direct_light(&mesh.material, base_color, hit_point_obj, n_obj, view_obj, mesh.receives_shadows, lights, cubes, meshes, textures, time, indirect.caustics.is_some());
+        if let Some(map) = indirect.caustics {
+            color = color + base_color * map.gather(hit_point_obj, n_obj);
         }
 
         // Reflection
-        if mesh.material.reflectivity > 0.0 {
-            let reflect_dir = ray.direction - normal * 2.0 * ray.direction.dot(normal);
-            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir);
-            let reflect_color = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, textures, depth + 1, time, world_angle);
+        if bounces && mesh.material.reflectivity > 0.0 {
+            let reflect_dir = reflect(ray.direction, normal);
+            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir)
+                .with_differential(bounce_differential(ray, hit_point, normal, |d| Some(reflect(d, normal))));
+            let reflect_color = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, indirect, textures, depth + 1, time, world_angle);
             color = color * (1.0 - mesh.material.reflectivity) + reflect_color * mesh.material.reflectivity;
         }
 
         // Refraccon
-        if mesh.material.transparency > 0.0 {
+        if bounces && mesh.material.transparency > 0.0 {
             let mut n = normal;
             let mut eta = 1.0 / mesh.material.refractive_index.max(1e-3);
             let cosi = (-ray.direction).dot(n).clamp(-1.0, 1.0);
@@ -394,15 +647,17 @@ fn cast_ray(
             let cosi_abs = cosi.abs();
             if !entering { n = -n; eta = 1.0 / eta; }
             let k = 1.0 - eta * eta * (1.0 - cosi_abs * cosi_abs);
-            let reflect_dir = ray.direction - normal * 2.0 * ray.direction.dot(normal);
-            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir);
-            let reflect_col = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, textures, depth + 1, time, world_angle);
+            let reflect_dir = reflect(ray.direction, normal);
+            let reflect_ray = CustomRay::new(hit_point + normal * 0.001, reflect_dir)
+                .with_differential(bounce_differential(ray, hit_point, normal, |d| Some(reflect(d, normal))));
+            let reflect_col = cast_ray(&reflect_ray, cubes, meshes, lights, skybox, indirect, textures, depth + 1, time, world_angle);
             let r0 = ((1.0 - mesh.material.refractive_index) / (1.0 + mesh.material.refractive_index)).powi(2);
             let fresnel = r0 + (1.0 - r0) * (1.0 - cosi_abs).powi(5);
             if k >= 0.0 {
                 let refract_dir = ray.direction * eta + n * (eta * cosi_abs - k.sqrt());
-                let refr_ray = CustomRay::new(hit_point - n * 0.001, refract_dir.normalized());
-                let refr_col = cast_ray(&refr_ray, cubes, meshes, lights, skybox, textures, depth + 1, time, world_angle);
+                let refr_ray = CustomRay::new(hit_point - n * 0.001, refract_dir.normalized())
+                    .with_differential(bounce_differential(ray, hit_point, normal, |d| refract(d, n, eta)));
+                let refr_col = cast_ray(&refr_ray, cubes, meshes, lights, skybox, indirect, textures, depth + 1, time, world_angle);
                 color = color * (1.0 - mesh.material.transparency)
                     + (reflect_col * fresnel + refr_col * (1.0 - fresnel)) * mesh.material.transparency;
             } else {
@@ -429,12 +684,458 @@ fn load_meshes() -> Vec<Mesh> {
     )) { result.push(mesh); }
 
     // esfera de vidrio (igual la borre xq no la uso)
+    // el vidrio se ve por refraccion: la luz directa encima solo lo ensucia
     if let Ok(mesh) = Mesh::from_obj("assets/models/sphere.obj", Vector3::new(-4.0, 0.0, -2.0), 1.0, material::Material::glass()) {
-        result.push(mesh);
+        result.push(mesh.with_shadows(true, false));
     }
     result
 }
 
+// luz ambiental del cielo: irradiancia difusa y, en superficies con brillo que no
+// trazan su propio reflejo, el cielo prefiltrado segun la rugosidad
+// irradiance: luz difusa que llega (del cielo o de las sondas)
+fn ambient_light(skybox: &Skybox, material: &Material, base_color: CustomColor, irradiance: CustomColor, normal: Vector3, view_dir: Vector3) -> CustomColor {
+    let mut ambient = base_color * irradiance;
+    if material.specular > 0.0 && material.reflectivity == 0.0 && material.transparency == 0.0 {
+        let reflect_dir = reflect(-view_dir, normal);
+        let cos_theta = normal.dot(view_dir).max(0.0);
+        let fresnel = 0.04 + 0.96 * (1.0 - cos_theta).powi(5);
+        ambient = ambient + skybox.prefiltered(reflect_dir, 1.0 - material.specular) * (material.specular * fresnel);
+    }
+    ambient
+}
+
+// luz directa de las luces de la escena: cada muestra de la luz con su propio rayo de sombra
+// (todo en el espacio del diorama, donde estan las luces y los objetos que tapan)
+fn direct_light(
+    material: &Material,
+    base_color: CustomColor,
+    point_obj: Vector3,
+    normal_obj: Vector3,
+    view_obj: Vector3,
+    receives_shadows: bool,
+    lights: &LightSet,
+    cubes: &[Cube],
+    meshes: &[Mesh],
+    textures: &TextureManager,
+    time: f32,
+    photons_refract: bool,
+) -> CustomColor {
+    let mut color = CustomColor::black();
+    lights.select(point_obj, LIGHTS_PER_POINT, |l, selection_weight| {
+        let mut light_color = CustomColor::black();
+        let count = l.samples(point_obj, |sample| {
+            if sample.weight <= 0.0 { return; }
+            let transmittance = if receives_shadows {
+                let shadow_ray = CustomRay::new(point_obj + normal_obj * 0.002, sample.direction);
+                occlusion(&shadow_ray, sample.distance, cubes, meshes, textures, time, photons_refract)
+            } else {
+                CustomColor::white()
+            };
+            if is_black(transmittance) { return; }
+
+            let ndotl = normal_obj.dot(sample.direction).max(0.0);
+            let half_dir = (sample.direction + view_obj).normalized();
+            let specular = normal_obj.dot(half_dir).max(0.0).powf(32.0) * material.specular;
+            light_color = light_color + (base_color * ndotl + CustomColor::white() * specular) * transmittance * sample.weight;
+        });
+        if count == 0 { return; }
+        color = color + light_color * (l.intensity * selection_weight / count as f32) * l.color;
+    });
+    color
+}
+
+// oclusion ambiental sobre la luz ambiente e indirecta
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AoMode {
+    Off,
+    // AO_RAYS rayos en el hemisferio hasta AO_DISTANCE
+    RayTraced,
+    // los 8 bloques vecinos de la cara (barato)
+    Voxel,
+}
+
+// iluminacion indirecta opcional que comparten todos los rayos
+struct Indirect<'a> {
+    grid: &'a LightGrid,
+    probes: Option<&'a ProbeGrid>,
+    // con causticas la luz que cruza medios refractivos llega por fotones, no por sombras
+    caustics: Option<&'a CausticMap>,
+    block_light: bool,
+    ao: AoMode,
+    // mostrar solo la oclusion ambiental
+    ao_debug: bool,
+    // rayo de una sonda: sombrea la superficie sin mezclar reflejo ni refraccion
+    // (su siguiente rebote pasaria MAX_DEPTH y volveria negro)
+    probe_ray: bool,
+}
+
+// con luz por bloques el cielo solo llega hasta donde llega la luz de cielo
+// (adentro de la casa queda oscuro) y los bloques emisivos suman su luz
+// (con sondas no hace falta: ellas ya ven las paredes y los emisivos)
+fn block_light(indirect: &Indirect, ambient: CustomColor, base_color: CustomColor, point: Vector3, normal: Vector3) -> CustomColor {
+    if !indirect.block_light || indirect.probes.is_some() {
+        return ambient;
+    }
+    let (sky, block) = indirect.grid.sample(point, normal);
+    ambient * sky + base_color * block * BLOCK_LIGHT_STRENGTH
+}
+
+// luz difusa que llega a la superficie: de las sondas si estan listas, si no del cielo
+fn diffuse_irradiance(indirect: &Indirect, skybox: &Skybox, point_obj: Vector3, normal_obj: Vector3, normal: Vector3) -> CustomColor {
+    indirect
+        .probes
+        .and_then(|probes| probes.irradiance(point_obj, normal_obj))
+        .unwrap_or_else(|| skybox.irradiance(normal))
+}
+
+// distancia al primer objeto que corta el rayo (espacio del diorama)
+fn hit_distance(ray: &CustomRay, cubes: &[Cube], meshes: &[Mesh], textures: &TextureManager, time: f32) -> f32 {
+    nearest_hit(ray, cubes, meshes, textures, time).map_or(f32::INFINITY, |(t, _, _)| t)
+}
+
+// primer objeto que corta el rayo: distancia, el cubo (si es un cubo) y su material
+fn nearest_hit<'a>(
+    ray: &CustomRay,
+    cubes: &'a [Cube],
+    meshes: &'a [Mesh],
+    textures: &TextureManager,
+    time: f32,
+) -> Option<(f32, Option<&'a Cube>, &'a Material)> {
+    let mut closest_t = f32::INFINITY;
+    let mut closest = None;
+    for cube in cubes {
+        if let Some((t, _, _)) = intersect_cube(cube, ray, textures, time) {
+            if t > 0.001 && t < closest_t {
+                closest_t = t;
+                closest = Some((t, Some(cube), &cube.material));
+            }
+        }
+    }
+    for mesh in meshes {
+        if let Some((t, _, _, _)) = mesh.intersect_with_uv_normal(ray) {
+            if t > 0.001 && t < closest_t {
+                closest_t = t;
+                closest = Some((t, None, &mesh.material));
+            }
+        }
+    }
+    closest
+}
+
+// fraccion de luz ambiente que llega a un punto (espacio del diorama); 1 = sin ocluir
+fn ambient_occlusion(
+    indirect: &Indirect,
+    point: Vector3,
+    normal: Vector3,
+    cubes: &[Cube],
+    meshes: &[Mesh],
+    textures: &TextureManager,
+    time: f32,
+) -> f32 {
+    match indirect.ao {
+        AoMode::Off => 1.0,
+        AoMode::Voxel => indirect.grid.voxel_ao(point, normal),
+        AoMode::RayTraced => {
+            // hemisferio con peso coseno, estratificado en anillos y girado por punto
+            let helper = if normal.y.abs() < 0.9 { Vector3::new(0.0, 1.0, 0.0) } else { Vector3::new(1.0, 0.0, 0.0) };
+            let tangent = normal.cross(helper).normalized();
+            let bitangent = normal.cross(tangent);
+            let rotation = hash_point(point) * std::f32::consts::TAU;
+            let origin = point + normal * 0.002;
+            let mut open = 0.0;
+            for i in 0..AO_RAYS {
+                let r = ((i as f32 + 0.5) / AO_RAYS as f32).sqrt();
+                let phi = rotation + i as f32 * 2.399963; // angulo aureo
+                let dir = tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * (1.0 - r * r).sqrt();
+                let through = occlusion(&CustomRay::new(origin, dir), AO_DISTANCE, cubes, meshes, textures, time, false);
+                open += (through.r + through.g + through.b) / 3.0;
+            }
+            open / AO_RAYS as f32
+        }
+    }
+}
+
+// interseccion con prueba de alfa: en materiales cutout los texeles transparentes no
+// bloquean el rayo y puede pegar en la cara de atras del mismo cubo
+fn intersect_cube(cube: &Cube, ray: &CustomRay, textures: &TextureManager, time: f32) -> Option<(f32, f32, f32)> {
+    let AlphaMode::Cutout(threshold) = cube.material.alpha_mode else {
+        return cube.intersect_with_uv(ray);
+    };
+    let (t_enter, t_exit) = cube.intersect_interval(ray)?;
+    for t in [t_enter, t_exit] {
+        if t <= 0.001 { continue; }
+        let point = ray.at(t);
+        let (u, v) = cube.uv_at(point);
+        let alpha = cube.material
+            .texture_for_face(cube.normal_at(point), textures)
+            .map(|name| textures.alpha(name, u, v, time, &cube.material))
+            .unwrap_or(1.0);
+        if alpha >= threshold {
+            return Some((t, u, v));
+        }
+    }
+    None
+}
+
+// luz que llega por un rayo de sombra hasta max_dist, probando todas las primitivas
+// (rayo en espacio del diorama): lo opaco la corta, el vidrio y el agua la atenuan
+// y la tiñen con su albedo; los objetos con casts_shadows = false no cuentan
+// con photons_refract la luz que atraviesa bloques de agua o vidrio la llevan los fotones y
+// aca se corta; las mallas no emiten fotones, asi que siguen dejando pasar su parte
+fn occlusion(ray: &CustomRay, max_dist: f32, cubes: &[Cube], meshes: &[Mesh], textures: &TextureManager, time: f32, photons_refract: bool) -> CustomColor {
+    let mut transmittance = CustomColor::white();
+    for oc in cubes.iter().filter(|c| c.casts_shadows) {
+        if let Some((t, u, v)) = intersect_cube(oc, ray, textures, time) {
+            if t > 0.001 && t < max_dist - 0.001 {
+                // con Blend los texels transparentes dejan pasar la luz (el centro de la ventana)
+                let alpha = if oc.material.alpha_mode == AlphaMode::Blend {
+                    oc.material
+                        .texture_for_face(oc.normal_at(ray.at(t)), textures)
+                        .map(|name| textures.alpha(name, u, v, time, &oc.material))
+                        .unwrap_or(1.0)
+                } else {
+                    1.0
+                };
+                transmittance = transmittance * shadow_filter(&oc.material, alpha, photons_refract);
+                if is_black(transmittance) {
+                    return CustomColor::black();
+                }
+            }
+        }
+    }
+    for om in meshes.iter().filter(|m| m.casts_shadows) {
+        if let Some((t, _, _, _)) = om.intersect_with_uv_normal(ray) {
+            if t > 0.001 && t < max_dist - 0.001 {
+                transmittance = transmittance * shadow_filter(&om.material, 1.0, false);
+                if is_black(transmittance) {
+                    return CustomColor::black();
+                }
+            }
+        }
+    }
+    transmittance
+}
+
+// fraccion de luz que cruza un objeto; alpha es la cobertura del texel en materiales Blend
+fn shadow_filter(material: &Material, alpha: f32, photons_refract: bool) -> CustomColor {
+    let through = if photons_refract { CustomColor::black() } else { material.albedo * material.transparency };
+    if material.alpha_mode == AlphaMode::Blend {
+        CustomColor::white() * (1.0 - alpha) + through * alpha
+    } else {
+        through
+    }
+}
+
+fn is_black(c: CustomColor) -> bool {
+    c.r.max(c.g).max(c.b) < 1e-3
+}
+
+// fotones de todas las luces a traves de los bloques transparentes (agua, vidrio);
+// solo se guardan los que terminan en una superficie difusa: luz -> agua/vidrio -> difusa
+fn shoot_photons(lights: &[Light], cubes: &[Cube], meshes: &[Mesh], textures: &TextureManager, time: f32) -> Vec<Photon> {
+    let scene = PhotonScene { cubes, meshes, textures, time };
+    let casters: Vec<&Cube> = cubes.iter().filter(|c| c.material.transparency > 0.0 && c.casts_shadows).collect();
+    let jobs: Vec<(usize, usize)> = (0..lights.len())
+        .flat_map(|l| (0..casters.len()).map(move |c| (l, c)))
+        .collect();
+    jobs.into_par_iter()
+        .flat_map_iter(|(l, c)| scene.emit(&lights[l], casters[c], (l * 7919 + c) as u64))
+        .collect()
+}
+
+// lo que necesita un foton para recorrer la escena (espacio del diorama)
+struct PhotonScene<'a> {
+    cubes: &'a [Cube],
+    meshes: &'a [Mesh],
+    textures: &'a TextureManager,
+    time: f32,
+}
+
+impl PhotonScene<'_> {
+    // fotones que entran al bloque por las caras que ven la luz. Cada uno lleva la luz
+    // que llega a su punto de la cara por el area que le toca (flujo)
+    fn emit(&self, light: &Light, caster: &Cube, seed: u64) -> Vec<Photon> {
+        let h = caster.size * 0.5;
+        // puntos de la cara pegados a otro bloque o metidos en uno (el borde del estanque
+        // queda dentro del suelo): esa luz ya entra por el vecino o no llega
+        let covered = |p: Vector3| {
+            self.cubes.iter().filter(|c| !std::ptr::eq(*c, caster)).any(|c| {
+                let ch = c.size * 0.5;
+                (p.x - c.center.x).abs() < ch && (p.y - c.center.y).abs() < ch && (p.z - c.center.z).abs() < ch
+            })
+        };
+        let normals = [
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+        ];
+        let lit = normals.map(|n| {
+            let mut reached = false;
+            if !covered(caster.center + n * (h + 0.01)) {
+                light.samples(caster.center + n * h, |s| reached |= s.weight > 0.0 && s.direction.dot(n) > 0.0);
+            }
+            reached
+        });
+        let lit_faces = lit.iter().filter(|&&l| l).count();
+        if lit_faces == 0 {
+            return Vec::new();
+        }
+
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        };
+
+        let per_face = PHOTONS_PER_CASTER / lit_faces;
+        let area = caster.size * caster.size;
+        let mut photons = Vec::new();
+        for n in normals.into_iter().zip(lit).filter(|&(_, l)| l).map(|(n, _)| n) {
+            let (t1, t2) = if n.x != 0.0 {
+                (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0))
+            } else if n.y != 0.0 {
+                (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0))
+            } else {
+                (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0))
+            };
+            for _ in 0..per_face {
+                let point = caster.center + n * h
+                    + t1 * ((random() - 0.5) * caster.size)
+                    + t2 * ((random() - 0.5) * caster.size);
+                if covered(point + n * 0.01) || covered(point - n * 0.01) { continue; }
+                // una muestra de la luz al azar, sin juntarlas (reservorio de tamaño 1)
+                let mut picked: Option<LightSample> = None;
+                let mut seen = 0;
+                light.samples(point, |s| {
+                    seen += 1;
+                    if random() * seen as f32 <= 1.0 {
+                        picked = Some(s);
+                    }
+                });
+                let Some(sample) = picked else { continue; };
+                let cos = n.dot(sample.direction);
+                if cos <= 0.0 || sample.weight <= 0.0 { continue; }
+                let shadow_ray = CustomRay::new(point + n * 0.002, sample.direction);
+                let shadow = occlusion(&shadow_ray, sample.distance, self.cubes, self.meshes, self.textures, self.time, false);
+                if is_black(shadow) { continue; }
+                // con Blend solo la parte cubierta del texel refracta; el resto pasa derecho (sombras)
+                let alpha = if caster.material.alpha_mode == AlphaMode::Blend {
+                    let (u, v) = caster.uv_at(point);
+                    caster.material
+                        .texture_for_face(n, self.textures)
+                        .map(|name| self.textures.alpha(name, u, v, self.time, &caster.material))
+                        .unwrap_or(1.0)
+                } else {
+                    1.0
+                };
+                let power = light.color * shadow * (light.intensity * sample.weight * cos * alpha * area / per_face as f32);
+                if let Some(photon) = self.trace(point, -sample.direction, n, caster, power) {
+                    photons.push(photon);
+                }
+            }
+        }
+        photons
+    }
+
+    // seguir un foton que entra al medio por point (normal de afuera) hasta que cae en
+    // algo difuso; None si se pierde, queda atrapado o pega en algo que no lo guarda
+    fn trace(&self, point: Vector3, dir: Vector3, normal: Vector3, first: &Cube, power: CustomColor) -> Option<Photon> {
+        let (mut point, mut dir, mut normal, mut medium, mut power) = (point, dir, normal, first, power);
+        for _ in 0..MAX_PHOTON_BOUNCES {
+            // entrar: lo que refleja la superficie no entra, el medio tiñe
+            let ior = medium.material.refractive_index.max(1e-3);
+            let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+            let cosi = (-dir).dot(normal).clamp(0.0, 1.0);
+            let fresnel = r0 + (1.0 - r0) * (1.0 - cosi).powi(5);
+            power = power * medium.material.albedo * (medium.material.transparency * (1.0 - fresnel));
+            dir = refract(dir, normal, 1.0 / ior)?.normalized();
+
+            // cruzar el medio; los bloques transparentes pegados (el resto del estanque)
+            // cuentan como el mismo medio
+            let mut crossed = 0;
+            loop {
+                let ray = CustomRay::new(point, dir);
+                let (_, t_exit) = medium.intersect_interval(&ray)?;
+                match self.nearest(&ray) {
+                    Some((t, Some(next), material)) if t < t_exit && material.transparency > 0.0 && crossed < MAX_PHOTON_BOUNCES => {
+                        power = power * material.albedo * material.transparency;
+                        medium = next;
+                        point = ray.at(t);
+                        crossed += 1;
+                    }
+                    // el fondo del estanque esta dentro del agua
+                    Some((t, _, material)) if t < t_exit => return store_photon(ray.at(t), dir, power, material),
+                    _ => {
+                        point = ray.at(t_exit);
+                        break;
+                    }
+                }
+            }
+
+            // salir al aire
+            dir = refract(dir, -medium.normal_at(point), ior)?.normalized();
+            let ray = CustomRay::new(point, dir);
+            let (t, next, material) = self.nearest(&ray)?;
+            point = ray.at(t);
+            match next {
+                Some(cube) if material.transparency > 0.0 => {
+                    medium = cube;
+                    normal = cube.normal_at(point);
+                }
+                _ => return store_photon(point, dir, power, material),
+            }
+        }
+        None
+    }
+
+    fn nearest(&self, ray: &CustomRay) -> Option<(f32, Option<&Cube>, &Material)> {
+        nearest_hit(ray, self.cubes, self.meshes, self.textures, self.time)
+    }
+}
+
+// solo las superficies difusas guardan fotones (el vidrio de las mallas no se sigue)
+fn store_photon(position: Vector3, direction: Vector3, power: CustomColor, material: &Material) -> Option<Photon> {
+    if material.transparency > 0.0 || material.emission.is_some() {
+        return None;
+    }
+    Some(Photon { position, direction, power })
+}
+
+fn reflect(d: Vector3, n: Vector3) -> Vector3 {
+    d - n * 2.0 * d.dot(n)
+}
+
+// n apunta hacia el lado de donde viene d; None si hay reflexion interna total
+fn refract(d: Vector3, n: Vector3, eta: f32) -> Option<Vector3> {
+    let cosi = (-d).dot(n).clamp(-1.0, 1.0);
+    let k = 1.0 - eta * eta * (1.0 - cosi * cosi);
+    if k < 0.0 { None } else { Some(d * eta + n * (eta * cosi - k.sqrt())) }
+}
+
+// rayos vecinos despues de rebotar: salen de donde cortan el plano del impacto
+// y se reflejan/refractan igual que el rayo principal
+fn bounce_differential(
+    ray: &CustomRay,
+    hit_point: Vector3,
+    normal: Vector3,
+    bounce: impl Fn(Vector3) -> Option<Vector3>,
+) -> Option<RayDifferential> {
+    let d = ray.differential?;
+    let (dpdx, dpdy) = d.hit_offsets(hit_point, normal)?;
+    Some(RayDifferential {
+        rx_origin: hit_point + dpdx,
+        rx_direction: bounce(d.rx_direction)?.normalized(),
+        ry_origin: hit_point + dpdy,
+        ry_direction: bounce(d.ry_direction)?.normalized(),
+    })
+}
+
 // funciones de rotación en eje Y
 fn rotate_vec3_y(v: Vector3, angle: f32) -> Vector3 {
     let (s, c) = angle.sin_cos();
@@ -445,7 +1146,22 @@ fn rotate_ray_y(ray: &CustomRay, angle: f32) -> CustomRay {
     CustomRay::new(rotate_vec3_y(ray.origin, angle), rotate_vec3_y(ray.direction, angle).normalized())
 }
 
-fn create_scene() -> Vec<Cube> {
+// "ruta@x,y,z[,tamaño]" -> (ruta, posicion de la base, tamaño de cada voxel);
+// sin @ va en MODEL_POSITION y sin tamaño usa MODEL_VOXEL_SIZE
+fn model_placement(arg: &str) -> (&str, Vector3, f32) {
+    let Some((path, at)) = arg.rsplit_once('@') else {
+        return (arg, MODEL_POSITION, MODEL_VOXEL_SIZE);
+    };
+    let values: Vec<f32> = at.split(',').filter_map(|v| v.trim().parse().ok()).collect();
+    let scale = values.get(3).copied().unwrap_or(MODEL_VOXEL_SIZE);
+    if values.len() != at.split(',').count() || !(3..=4).contains(&values.len()) || scale <= 0.0 {
+        eprintln!("Posicion invalida en {} (se usa x,y,z o x,y,z,tamaño)", arg);
+        return (path, MODEL_POSITION, MODEL_VOXEL_SIZE);
+    }
+    (path, Vector3::new(values[0], values[1], values[2]), scale)
+}
+
+fn create_scene(models: &[String]) -> Vec<Cube> {
     let mut cubes = Vec::new();
 
     // suelo
@@ -462,7 +1178,8 @@ fn create_scene() -> Vec<Cube> {
     // paredes de la casa
     for y in 0..3 {
         for x in -2..3 {
-            if !(y == 0 && x == 0) {
+            // huecos de la puerta y las ventanas
+            if !(y == 0 && x == 0) && !(y == 1 && (x == -1 || x == 1)) {
                 cubes.push(Cube::new(
                     Vector3::new(x as f32, y as f32, -2.0),
                     1.0,
@@ -546,12 +1263,12 @@ fn create_scene() -> Vec<Cube> {
         }
     }
 
-    // fogata
+    // fogata: es la llama, no tapa la luz de las demas
     cubes.push(Cube::new(
         Vector3::new(-3.0, 1.0, -3.0),
         0.5,
         Material::fire()
-    ));
+    ).with_shadows(false, true));
 
     // rocas 
     cubes.push(Cube::new(Vector3::new(-4.0, -0.3, -4.0), 0.6, Material::stone()));
@@ -561,6 +1278,67 @@ fn create_scene() -> Vec<Cube> {
     cubes.push(Cube::new(Vector3::new(-5.0, -0.4, 1.0), 0.5, Material::stone()));
     cubes.push(Cube::new(Vector3::new(1.5, -0.35, -5.0), 0.6, Material::stone()));
 
+    // modelos externos pedidos por argumento (ruta[@x,y,z[,tamaño]]); sin argumentos no se carga nada
+    // las construcciones de Minecraft (.schem / .nbt) usan una tabla de bloques configurable
+    let mut block_mapping: Option<schematic::BlockMapping> = None;
+    for arg in models {
+        let (path, position, scale) = model_placement(arg);
+        let loaded = if path.ends_with(".vox") {
+            vox::load_vox(path, position, scale)
+        } else if path.ends_with(".schem") || path.ends_with(".nbt") {
+            let mapping = block_mapping.get_or_insert_with(|| {
+                match schematic::BlockMapping::from_file("assets/models/blocks.txt") {
+                    Ok(mapping) => mapping,
+                    Err(e) => {
+                        if e.kind() != std::io::ErrorKind::NotFound {
+                            eprintln!("No cargo la tabla de bloques: {}", e);
+                        }
+                        schematic::BlockMapping::new()
+                    }
+                }
+            });
+            schematic::load_schematic(path, position, scale, mapping)
+        } else {
+            eprintln!("Formato no soportado: {}", path);
+            continue;
+        };
+        match loaded {
+            Ok(model_cubes) => {
+                println!("Modelo {} cargado con {} bloques", path, model_cubes.len());
+                cubes.extend(model_cubes);
+            }
+            Err(e) => eprintln!("No cargo {}: {}", path, e),
+        }
+    }
+    if models.is_empty() {
+        println!("Sin modelos externos (pasar rutas .vox, .schem o .nbt como argumentos)");
+    }
+
     println!("Scene created with {} cubes", cubes.len());
     cubes
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_spec_reads_position_and_optional_scale() {
+        let (path, position, scale) = model_placement("casa.vox@4,0,-4");
+        assert_eq!(path, "casa.vox");
+        assert_eq!((position.x, position.y, position.z), (4.0, 0.0, -4.0));
+        assert_eq!(scale, MODEL_VOXEL_SIZE);
+
+        let (_, position, scale) = model_placement("castillo.schem@-4,1,0,0.5");
+        assert_eq!((position.x, position.y, position.z), (-4.0, 1.0, 0.0));
+        assert_eq!(scale, 0.5);
+
+        assert_eq!(model_placement("arbol.vox").2, MODEL_VOXEL_SIZE);
+        // tamaño invalido o sobrante: se vuelve a los valores por defecto
+        for bad in ["m.vox@1,2,3,-1", "m.vox@1,2,3,4,5", "m.vox@1,x,3"] {
+            let (path, position, scale) = model_placement(bad);
+            assert_eq!(path, "m.vox");
+            assert_eq!(position.z, MODEL_POSITION.z);
+            assert_eq!(scale, MODEL_VOXEL_SIZE);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 // material.rs
+use raylib::prelude::*;
 use crate::color::Color;
+use crate::texture::{Sampler, TextureFilter, TextureManager, WrapMode};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MaterialType {
@@ -9,6 +11,57 @@ pub enum MaterialType {
     Emissive,
 }
 
+// como se usa el alfa de la textura
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    Cutout(f32), // los texeles con alfa menor al umbral no existen (hojas)
+    Blend,       // se mezcla con lo que hay detras segun el alfa (vidrio)
+}
+
+// texturas por direccion de cara (pasto arriba, tierra abajo, etc.)
+// None en una cara usa el texture_name del material
+#[derive(Clone, Default)]
+pub struct FaceTextures {
+    pub pos_x: Option<String>,
+    pub neg_x: Option<String>,
+    pub pos_y: Option<String>,
+    pub neg_y: Option<String>,
+    pub pos_z: Option<String>,
+    pub neg_z: Option<String>,
+}
+
+impl FaceTextures {
+    pub fn all(name: &str) -> Self {
+        FaceTextures::top_side_bottom(name, name, name)
+    }
+
+    pub fn top_side_bottom(top: &str, side: &str, bottom: &str) -> Self {
+        FaceTextures {
+            pos_x: Some(side.to_string()),
+            neg_x: Some(side.to_string()),
+            pos_y: Some(top.to_string()),
+            neg_y: Some(bottom.to_string()),
+            pos_z: Some(side.to_string()),
+            neg_z: Some(side.to_string()),
+        }
+    }
+
+    // normal en espacio objeto, alineada a un eje
+    pub fn for_normal(&self, normal: Vector3) -> Option<&String> {
+        let (ax, ay, az) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+        if ax >= ay && ax >= az {
+            if normal.x > 0.0 { self.pos_x.as_ref() } else { self.neg_x.as_ref() }
+        } else if ay >= az {
+            if normal.y > 0.0 { self.pos_y.as_ref() } else { self.neg_y.as_ref() }
+        } else if normal.z > 0.0 {
+            self.pos_z.as_ref()
+        } else {
+            self.neg_z.as_ref()
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Material {
     pub material_type: MaterialType,
@@ -20,6 +73,9 @@ pub struct Material {
     pub emission: Option<Color>,
     pub texture_name: Option<String>,
     pub animated: bool,   
+    pub face_textures: Option<FaceTextures>,
+    pub sampler: Sampler, // filtrado y wrap de la textura
+    pub alpha_mode: AlphaMode,
 }
 
 impl Material {
@@ -44,10 +100,62 @@ impl Material {
             emission,
             texture_name,
             animated,
+            face_textures: None,
+            sampler: Sampler::default(),
+            alpha_mode: AlphaMode::Opaque,
         }
     }
 
-    // materiales predefinidos
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    // no deja ver lo que hay detras (para descartar caras tapadas)
+    pub fn is_opaque(&self) -> bool {
+        self.transparency == 0.0 && self.alpha_mode == AlphaMode::Opaque
+    }
+
+    pub fn with_face_textures(mut self, faces: FaceTextures) -> Self {
+        self.face_textures = Some(faces);
+        self
+    }
+
+    // textura de la cara golpeada; si la textura de cara no esta cargada se usa texture_name
+    pub fn texture_for_face(&self, normal: Vector3, textures: &TextureManager) -> Option<&str> {
+        self.face_textures
+            .as_ref()
+            .and_then(|faces| faces.for_normal(normal))
+            .filter(|name| textures.has_texture(name))
+            .or(self.texture_name.as_ref())
+            .map(|name| name.as_str())
+    }
+
+    // buscar un material predefinido por nombre (tablas de mapeo, archivos)
+    pub fn from_preset(name: &str) -> Option<Self> {
+        match name {
+            "grass" => Some(Material::grass()),
+            "dirt" => Some(Material::dirt()),
+            "wood" => Some(Material::wood()),
+            "water" => Some(Material::water()),
+            "glass" => Some(Material::glass()),
+            "stone" => Some(Material::stone()),
+            "leaves" => Some(Material::leaves()),
+            "brick" => Some(Material::brick()),
+            "woodhouse" => Some(Material::woodhouse()),
+            "fire" => Some(Material::fire()),
+            "torch" => Some(Material::torch()),
+            "lamp" => Some(Material::lamp()),
+            _ => None,
+        }
+    }
+
+    // materiales predefinidos (colores elegidos en sRGB, pasados a lineal)
 
     pub fn grass() -> Self {
         Material::new(
@@ -61,6 +169,24 @@ impl Material {
             Some("grass".to_string()),
             false,
         )
+        .with_face_textures(FaceTextures::top_side_bottom("grass", "grass_side", "dirt"))
+        // el piso se ve de lejos: mipmaps para evitar aliasing
+        .with_sampler(Sampler::new(TextureFilter::Trilinear, WrapMode::Repeat))
+    }
+
+    // tierra sola (sin el pasto de arriba): dirt.png o un marron liso
+    pub fn dirt() -> Self {
+        Material::new(
+            MaterialType::Diffuse,
+            Color::new(1.0, 1.0, 1.0),
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+            None,
+            Some("dirt".to_string()),
+            false,
+        )
     }
 
     pub fn wood() -> Self {
@@ -75,12 +201,13 @@ impl Material {
             Some("wood".to_string()),
             false,
         )
+        .with_face_textures(FaceTextures::top_side_bottom("wood_top", "wood", "wood_top"))
     }
 
     pub fn water() -> Self {
         Material::new(
             MaterialType::Reflective,
-            Color::new(0.9, 0.95, 1.0),
+            Color::from_srgb(0.9, 0.95, 1.0),
             0.8,
             0.6,
             0.3,
@@ -89,12 +216,13 @@ impl Material {
             Some("water".to_string()),
             true,
         )
+        .with_sampler(Sampler::new(TextureFilter::Trilinear, WrapMode::Repeat))
     }
 
     pub fn glass() -> Self {
         Material::new(
             MaterialType::Refractive,
-            Color::new(0.95, 0.95, 1.0),
+            Color::from_srgb(0.95, 0.95, 1.0),
             0.9,
             0.4,
             0.8,
@@ -103,6 +231,10 @@ impl Material {
             Some("glass".to_string()),
             false,
         )
+        // el marco de la ventana no debe repetirse en el borde
+        .with_sampler(Sampler::new(TextureFilter::Bilinear, WrapMode::Clamp))
+        // el centro de glass.png es transparente: solo el marco refracta
+        .with_alpha_mode(AlphaMode::Blend)
     }
 
     pub fn stone() -> Self {
@@ -117,12 +249,14 @@ impl Material {
             Some("stone".to_string()),
             false,
         )
+        // la piedra no empalma en los bordes: al filtrar de lejos se espeja en vez de mezclar el lado opuesto
+        .with_sampler(Sampler::new(TextureFilter::Trilinear, WrapMode::Mirror))
     }
 
     pub fn leaves() -> Self {
         Material::new(
             MaterialType::Diffuse,
-            Color::new(0.8, 1.0, 0.8),
+            Color::from_srgb(0.8, 1.0, 0.8),
             0.0,
             0.0,
             0.0,
@@ -131,6 +265,9 @@ impl Material {
             Some("grass".to_string()),
             false,
         )
+        // leaves.png con huecos si existe, si no el pasto
+        .with_face_textures(FaceTextures::all("leaves"))
+        .with_alpha_mode(AlphaMode::Cutout(0.5))
     }
 
     pub fn brick() -> Self {
@@ -164,12 +301,12 @@ impl Material {
     pub fn fire() -> Self {
         Material::new(
             MaterialType::Emissive,
-            Color::new(1.0, 0.8, 0.2),
+            Color::from_srgb(1.0, 0.8, 0.2),
             0.0,
             0.0,
             0.0,
             1.0,
-            Some(Color::new(1.0, 0.6, 0.1) * 8.0),
+            Some(Color::from_srgb(1.0, 0.6, 0.1) * 8.0),
             Some("fire".to_string()),
             true,
         )
@@ -178,12 +315,12 @@ impl Material {
     pub fn torch() -> Self {
         Material::new(
             MaterialType::Emissive,
-            Color::new(1.0, 0.6, 0.2),
+            Color::from_srgb(1.0, 0.6, 0.2),
             0.0,
             0.0,
             0.0,
             1.0,
-            Some(Color::new(1.0, 0.5, 0.1) * 3.0), 
+            Some(Color::from_srgb(1.0, 0.5, 0.1) * 3.0), 
             None,
             false,
         )
@@ -192,12 +329,12 @@ impl Material {
     pub fn lamp() -> Self {
         Material::new(
             MaterialType::Emissive,
-            Color::new(1.0, 1.0, 0.8),
+            Color::from_srgb(1.0, 1.0, 0.8),
             0.0,
             0.0,
             0.0,
             1.0,
-            Some(Color::new(1.0, 1.0, 0.8) * 2.0),
+            Some(Color::from_srgb(1.0, 1.0, 0.8) * 2.0),
             None,
             false,
         )
@@ -17,6 +17,8 @@ pub struct Mesh {
     pub position: Vector3,
     pub scale: f32,
     pub material: Material,
+    pub casts_shadows: bool,
+    pub receives_shadows: bool,
 }
 
 impl Mesh {
@@ -87,7 +89,13 @@ impl Mesh {
             }
         }
 
-        Ok(Mesh { triangles, position, scale, material })
+        Ok(Mesh { triangles, position, scale, material, casts_shadows: true, receives_shadows: true })
+    }
+
+    pub fn with_shadows(mut self, casts: bool, receives: bool) -> Self {
+        self.casts_shadows = casts;
+        self.receives_shadows = receives;
+        self
     }
 
     // intersección rayo-mesh con UVs y normal
@@ -0,0 +1,346 @@
+// nbt.rs
+// lector de NBT (formato binario de Minecraft), usado por schematic.rs
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Read, Result};
+
+// los tipos que ningun importador lee se saltean sin guardar su valor
+#[derive(Debug, Clone)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float,
+    Double,
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray,
+}
+
+impl Tag {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    // enteros de cualquier ancho como i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v as i64),
+            Tag::Short(v) => Some(v as i64),
+            Tag::Int(v) => Some(v as i64),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    // listas de enteros: IntArray o List de Int
+    pub fn as_int_vec(&self) -> Option<Vec<i32>> {
+        match self {
+            Tag::IntArray(v) => Some(v.clone()),
+            Tag::List(items) => items.iter().map(|t| t.as_i64().map(|v| v as i32)).collect(),
+            _ => None,
+        }
+    }
+}
+
+// leer un archivo NBT (comprimido con gzip o sin comprimir)
+pub fn read_file(path: &str) -> Result<Tag> {
+    let raw = std::fs::read(path)?;
+    let data = if raw.starts_with(&[0x1f, 0x8b]) {
+        let mut out = Vec::new();
+        GzDecoder::new(&raw[..]).read_to_end(&mut out)?;
+        out
+    } else {
+        raw
+    };
+    parse(&data)
+}
+
+// el root siempre es un compound con nombre
+pub fn parse(data: &[u8]) -> Result<Tag> {
+    let mut r = Reader { data, pos: 0 };
+    let kind = r.u8()?;
+    if kind != 10 {
+        return Err(invalid("el root NBT no es un compound"));
+    }
+    let _name = r.string()?;
+    r.payload(kind, 0)
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+// lector big-endian
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.pos + n > self.data.len() {
+            return Err(invalid("NBT truncado"));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        let b = self.bytes(2)?;
+        Ok(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        let b = self.bytes(4)?;
+        Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        let b = self.bytes(8)?;
+        let mut a = [0u8; 8];
+        a.copy_from_slice(b);
+        Ok(i64::from_be_bytes(a))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        let n = self.i32()?;
+        if n < 0 { Ok(0) } else { Ok(n as usize) }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.i16()? as u16 as usize;
+        // modified UTF-8: suficiente con lossy para ids de bloques
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn payload(&mut self, kind: u8, depth: u32) -> Result<Tag> {
+        if depth > 512 {
+            return Err(invalid("NBT demasiado anidado"));
+        }
+        Ok(match kind {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => {
+                self.bytes(4)?;
+                Tag::Float
+            }
+            6 => {
+                self.bytes(8)?;
+                Tag::Double
+            }
+            7 => {
+                let n = self.len()?;
+                Tag::ByteArray(self.bytes(n)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_kind = self.u8()?;
+                let n = self.len()?;
+                let mut items = Vec::with_capacity(n.min(1 << 16));
+                for _ in 0..n {
+                    items.push(self.payload(item_kind, depth + 1)?);
+                }
+                Tag::List(items)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let child_kind = self.u8()?;
+                    if child_kind == 0 { break; }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(child_kind, depth + 1)?);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let n = self.len()?;
+                let mut v = Vec::with_capacity(n.min(1 << 16));
+                for _ in 0..n { v.push(self.i32()?); }
+                Tag::IntArray(v)
+            }
+            12 => {
+                let n = self.len()?;
+                self.bytes(n.checked_mul(8).ok_or_else(|| invalid("NBT truncado"))?)?;
+                Tag::LongArray
+            }
+            // End solo aparece como item de listas vacias
+            0 => Tag::List(Vec::new()),
+            _ => return Err(invalid("tipo de tag NBT desconocido")),
+        })
+    }
+}
+
+// escritor minimo para armar archivos en las pruebas
+#[cfg(test)]
+pub fn encode(root: &Tag) -> Vec<u8> {
+    fn kind(tag: &Tag) -> u8 {
+        match tag {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float => 5,
+            Tag::Double => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray => 12,
+        }
+    }
+    fn string(out: &mut Vec<u8>, s: &str) {
+        out.extend((s.len() as u16).to_be_bytes());
+        out.extend(s.as_bytes());
+    }
+    fn payload(out: &mut Vec<u8>, tag: &Tag) {
+        match tag {
+            Tag::Byte(v) => out.push(*v as u8),
+            Tag::Short(v) => out.extend(v.to_be_bytes()),
+            Tag::Int(v) => out.extend(v.to_be_bytes()),
+            Tag::Long(v) => out.extend(v.to_be_bytes()),
+            // sin valor guardado: se escribe 0
+            Tag::Float => out.extend(0f32.to_bits().to_be_bytes()),
+            Tag::Double => out.extend(0f64.to_bits().to_be_bytes()),
+            Tag::ByteArray(v) => {
+                out.extend((v.len() as i32).to_be_bytes());
+                out.extend(v.iter().map(|&b| b as u8));
+            }
+            Tag::String(s) => string(out, s),
+            Tag::List(items) => {
+                out.push(items.first().map(kind).unwrap_or(0));
+                out.extend((items.len() as i32).to_be_bytes());
+                for item in items { payload(out, item); }
+            }
+            Tag::Compound(map) => {
+                for (name, child) in map {
+                    out.push(kind(child));
+                    string(out, name);
+                    payload(out, child);
+                }
+                out.push(0);
+            }
+            Tag::IntArray(v) => {
+                out.extend((v.len() as i32).to_be_bytes());
+                for x in v { out.extend(x.to_be_bytes()); }
+            }
+            Tag::LongArray => out.extend(0i32.to_be_bytes()),
+        }
+    }
+    let mut out = vec![10];
+    string(&mut out, "");
+    payload(&mut out, root);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{gzip, TempFile};
+
+    #[test]
+    fn round_trip_every_tag_kind() {
+        let mut map = HashMap::new();
+        map.insert("byte".to_string(), Tag::Byte(-3));
+        map.insert("short".to_string(), Tag::Short(-300));
+        map.insert("int".to_string(), Tag::Int(70000));
+        map.insert("long".to_string(), Tag::Long(-5_000_000_000));
+        map.insert("float".to_string(), Tag::Float);
+        map.insert("double".to_string(), Tag::Double);
+        map.insert("bytes".to_string(), Tag::ByteArray(vec![1, -1]));
+        map.insert("name".to_string(), Tag::String("minecraft:stone".into()));
+        map.insert("list".to_string(), Tag::List(vec![Tag::Int(1), Tag::Int(2), Tag::Int(3)]));
+        map.insert("empty".to_string(), Tag::List(Vec::new()));
+        map.insert("ints".to_string(), Tag::IntArray(vec![4, -5]));
+        map.insert("longs".to_string(), Tag::LongArray);
+        let mut inner = HashMap::new();
+        inner.insert("x".to_string(), Tag::Int(9));
+        map.insert("inner".to_string(), Tag::Compound(inner));
+
+        let tag = parse(&encode(&Tag::Compound(map))).unwrap();
+        assert_eq!(tag.get("byte").and_then(Tag::as_i64), Some(-3));
+        assert_eq!(tag.get("short").and_then(Tag::as_i64), Some(-300));
+        assert_eq!(tag.get("int").and_then(Tag::as_i64), Some(70000));
+        assert_eq!(tag.get("long").and_then(Tag::as_i64), Some(-5_000_000_000));
+        assert!(matches!(tag.get("float"), Some(Tag::Float)));
+        assert!(matches!(tag.get("double"), Some(Tag::Double)));
+        assert!(matches!(tag.get("bytes"), Some(Tag::ByteArray(v)) if v == &[1, -1]));
+        assert_eq!(tag.get("name").and_then(Tag::as_str), Some("minecraft:stone"));
+        assert_eq!(tag.get("list").and_then(Tag::as_int_vec), Some(vec![1, 2, 3]));
+        assert_eq!(tag.get("empty").and_then(Tag::as_list).map(<[Tag]>::len), Some(0));
+        assert_eq!(tag.get("ints").and_then(Tag::as_int_vec), Some(vec![4, -5]));
+        assert!(matches!(tag.get("longs"), Some(Tag::LongArray)));
+        assert_eq!(tag.get("inner").and_then(|t| t.get("x")).and_then(Tag::as_i64), Some(9));
+    }
+
+    #[test]
+    fn skipped_payloads_keep_the_reader_aligned() {
+        // compound { "l": LongArray de 2, "i": Int 5 } escrito a mano: el arreglo tiene datos
+        let mut data = vec![10, 0, 0];
+        data.extend([12, 0, 1, b'l']);
+        data.extend(2i32.to_be_bytes());
+        data.extend(7i64.to_be_bytes());
+        data.extend((-8i64).to_be_bytes());
+        data.extend([3, 0, 1, b'i']);
+        data.extend(5i32.to_be_bytes());
+        data.push(0);
+        let tag = parse(&data).unwrap();
+        assert!(matches!(tag.get("l"), Some(Tag::LongArray)));
+        assert_eq!(tag.get("i").and_then(Tag::as_i64), Some(5));
+    }
+
+    #[test]
+    fn read_file_accepts_plain_and_gzip() {
+        let data = encode(&Tag::Compound(HashMap::from([("int".to_string(), Tag::Int(7))])));
+        for (name, bytes) in [("plain.nbt", data.clone()), ("gzip.nbt", gzip(&data))] {
+            let file = TempFile::new(name, &bytes);
+            let tag = read_file(&file.path).unwrap();
+            assert_eq!(tag.get("int").and_then(Tag::as_i64), Some(7), "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        // el root tiene que ser un compound
+        assert!(parse(&[8, 0, 0, 0, 0]).is_err());
+        let mut data = encode(&Tag::Compound(HashMap::from([("int".to_string(), Tag::Int(1))])));
+        data.truncate(data.len() - 2);
+        assert!(parse(&data).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+// photon.rs
+// mapa de fotones para causticas: los fotones que cruzan el agua o el vidrio y caen en
+// una superficie difusa se guardan en un kd-tree y al sombrear se cuentan los cercanos
+use raylib::prelude::*;
+use std::f32::consts::PI;
+use crate::blocklight;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::light::{Falloff, Light, LightKind};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Photon {
+    pub position: Vector3,  // espacio del diorama
+    pub direction: Vector3, // hacia donde viajaba al llegar
+    pub power: Color,       // flujo que lleva
+}
+
+// kd-tree balanceado guardado en el mismo arreglo: cada rango [lo, hi) tiene su
+// mediana en el medio y se parte por el eje depth % 3
+pub struct CausticMap {
+    photons: Vec<Photon>,
+    radius: f32,
+}
+
+// filtro de cono (Jensen): pesa mas a los fotones cercanos, bordes mas nitidos
+const CONE_K: f32 = 1.1;
+
+impl CausticMap {
+    pub fn build(mut photons: Vec<Photon>, radius: f32) -> Self {
+        build_tree(&mut photons, 0);
+        CausticMap { photons, radius }
+    }
+
+    // luz que dejan los fotones alrededor de un punto (irradiancia, como la luz directa):
+    // solo cuentan los que llegan por el lado de la normal
+    pub fn gather(&self, point: Vector3, normal: Vector3) -> Color {
+        let mut sum = Color::black();
+        let r2 = self.radius * self.radius;
+        self.visit(0, self.photons.len(), 0, point, r2, &mut |photon, dist2| {
+            if photon.direction.dot(normal) >= 0.0 {
+                return;
+            }
+            let w = 1.0 - dist2.sqrt() / (CONE_K * self.radius);
+            sum = sum + photon.power * w;
+        });
+        sum * (1.0 / ((1.0 - 2.0 / (3.0 * CONE_K)) * PI * r2))
+    }
+
+    // recorrer los fotones a menos de sqrt(r2) del punto
+    fn visit(&self, lo: usize, hi: usize, depth: usize, point: Vector3, r2: f32, f: &mut impl FnMut(&Photon, f32)) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let photon = &self.photons[mid];
+        let offset = photon.position - point;
+        let dist2 = offset.dot(offset);
+        if dist2 <= r2 {
+            f(photon, dist2);
+        }
+        let diff = axis_value(point, depth) - axis_value(photon.position, depth);
+        // primero el lado del punto; el otro solo si el plano queda dentro del radio
+        let (near, far) = if diff < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+        self.visit(near.0, near.1, depth + 1, point, r2, f);
+        if diff * diff <= r2 {
+            self.visit(far.0, far.1, depth + 1, point, r2, f);
+        }
+    }
+}
+
+// el mapa de fotones no cambia mientras no cambien las luces ni los bloques: se guarda
+// entre cuadros y se rehace solo cuando cambia su firma (como LightGrid::refresh)
+pub struct CausticCache {
+    map: Option<CausticMap>,
+    signature: u64,
+    // potencia de cada luz (color * intensidad) cuando se armo el mapa
+    powers: Vec<[f32; 3]>,
+    radius: f32,
+}
+
+// cuanto puede cambiar la potencia de una luz (relativo) antes de rehacer el mapa
+const POWER_TOLERANCE: f32 = 0.1;
+
+impl CausticCache {
+    pub fn new(radius: f32) -> Self {
+        CausticCache { map: None, signature: 0, powers: Vec::new(), radius }
+    }
+
+    // rehacer el mapa con los fotones de shoot si cambiaron las luces o los bloques;
+    // devuelve si lo rehizo
+    // la potencia se compara contra la del ultimo mapa y no por escalones: al amanecer
+    // el sol crece un poco cada cuadro y el mapa se rehace cada POWER_TOLERANCE, no
+    // cada vez que cruza un redondeo
+    pub fn refresh(&mut self, lights: &[Light], cubes: &[Cube], shoot: impl FnOnce() -> Vec<Photon>) -> bool {
+        let signature = light_signature(lights) ^ blocklight::signature(cubes).rotate_left(1);
+        let drifted = self.powers.len() != lights.len()
+            || lights.iter().zip(&self.powers).any(|(l, old)| {
+                power(l).iter().zip(old).any(|(&a, &b)| (a - b).abs() > POWER_TOLERANCE * a.max(b) + 1e-3)
+            });
+        if self.map.is_some() && signature == self.signature && !drifted {
+            return false;
+        }
+        self.signature = signature;
+        self.powers = lights.iter().map(power).collect();
+        self.map = Some(CausticMap::build(shoot(), self.radius));
+        true
+    }
+
+    // con las causticas apagadas se suelta el mapa; al volver se rehace
+    pub fn clear(&mut self) {
+        self.map = None;
+    }
+
+    pub fn map(&self) -> Option<&CausticMap> {
+        self.map.as_ref()
+    }
+}
+
+fn power(l: &Light) -> [f32; 3] {
+    [l.color.r * l.intensity, l.color.g * l.intensity, l.color.b * l.intensity]
+}
+
+// resumen de por donde van los fotones (la potencia se compara aparte). Las direcciones
+// se redondean: el sol avanza un poco cada cuadro y las causticas no cambian hasta que
+// se nota (1 grado corre la mancha menos de 2 cm en el fondo del estanque)
+fn light_signature(lights: &[Light]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    let mut mix = |v: f32, step: f32| {
+        h ^= (v / step).round() as i64 as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    };
+    for l in lights {
+        match l.kind {
+            LightKind::Directional { direction, angular_radius } => {
+                for v in [0.0, direction.x, direction.y, direction.z] { mix(v, 0.02); }
+                mix(angular_radius, 0.001);
+            }
+            LightKind::Point => mix(1.0, 1.0),
+            LightKind::Spot { direction, cos_inner, cos_outer } => {
+                for v in [2.0, direction.x, direction.y, direction.z, cos_inner, cos_outer] { mix(v, 0.001); }
+            }
+            LightKind::RectArea { u_axis, v_axis } => {
+                for v in [3.0, u_axis.x, u_axis.y, u_axis.z, v_axis.x, v_axis.y, v_axis.z] { mix(v, 0.001); }
+            }
+            LightKind::SphereArea { radius } => { mix(4.0, 1.0); mix(radius, 0.001); }
+            LightKind::CubeArea { half_size } => { mix(5.0, 1.0); mix(half_size, 0.001); }
+        }
+        if !matches!(l.kind, LightKind::Directional { .. }) {
+            for v in [l.position.x, l.position.y, l.position.z] { mix(v, 0.01); }
+        }
+        match l.falloff {
+            Falloff::None => mix(0.0, 1.0),
+            Falloff::InverseSquare => mix(1.0, 1.0),
+            Falloff::Polynomial { constant, linear, quadratic } => {
+                for v in [2.0, constant, linear, quadratic] { mix(v, 0.001); }
+            }
+            Falloff::Windowed { range } => { mix(3.0, 1.0); mix(range, 0.001); }
+        }
+        mix(l.shadow_grid as f32, 1.0);
+    }
+    h
+}
+
+fn build_tree(photons: &mut [Photon], depth: usize) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let mid = photons.len() / 2;
+    photons.select_nth_unstable_by(mid, |a, b| {
+        axis_value(a.position, depth).total_cmp(&axis_value(b.position, depth))
+    });
+    let (left, right) = photons.split_at_mut(mid);
+    build_tree(left, depth + 1);
+    build_tree(&mut right[1..], depth + 1);
+}
+
+fn axis_value(p: Vector3, depth: usize) -> f32 {
+    match depth % 3 {
+        0 => p.x,
+        1 => p.y,
+        _ => p.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+
+    // xorshift con semilla fija: el mismo conjunto en cada corrida
+    fn random_points(count: usize, seed: u64) -> Vec<Vector3> {
+        let mut state = seed | 1;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 40) as f32 / (1u64 << 24) as f32
+        };
+        (0..count)
+            .map(|_| Vector3::new(random() * 4.0 - 2.0, random() * 0.5, random() * 4.0 - 2.0))
+            .collect()
+    }
+
+    #[test]
+    fn radius_query_matches_brute_force() {
+        let photons: Vec<Photon> = random_points(500, 7)
+            .into_iter()
+            .map(|position| Photon { position, direction: Vector3::new(0.0, -1.0, 0.0), power: Color::white() })
+            .collect();
+        let map = CausticMap::build(photons.clone(), 0.3);
+        for (i, point) in random_points(50, 99).into_iter().enumerate() {
+            let r2 = if i % 2 == 0 { 0.09 } else { 0.6 };
+            let mut found = Vec::new();
+            map.visit(0, map.photons.len(), 0, point, r2, &mut |photon, dist2| found.push((photon.position.x, dist2)));
+            let mut expected: Vec<(f32, f32)> = photons
+                .iter()
+                .filter_map(|photon| {
+                    let offset = photon.position - point;
+                    let dist2 = offset.dot(offset);
+                    (dist2 <= r2).then_some((photon.position.x, dist2))
+                })
+                .collect();
+            found.sort_by(|a, b| a.0.total_cmp(&b.0));
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn cache_rebuilds_only_when_lights_or_blocks_change() {
+        let cubes = vec![Cube::new(Vector3::new(0.0, 0.0, 0.0), 1.0, Material::water())];
+        let sun = |x: f32| vec![Light::directional(Vector3::new(x, 1.0, 0.0), Color::white(), 1.0, 0.02)];
+        let mut cache = CausticCache::new(0.2);
+        let mut shots = 0;
+        let mut shoot = || {
+            shots += 1;
+            Vec::new()
+        };
+        assert!(cache.refresh(&sun(0.3), &cubes, &mut shoot));
+        // el sol se movio menos de lo que se nota
+        assert!(!cache.refresh(&sun(0.301), &cubes, &mut shoot));
+        assert!(cache.refresh(&sun(0.5), &cubes, &mut shoot));
+
+        // al amanecer la intensidad sube un poco cada cuadro: solo se rehace al pasar la tolerancia
+        let dawn = |intensity: f32| vec![Light::directional(Vector3::new(0.5, 1.0, 0.0), Color::new(1.0, 0.6, 0.3), intensity, 0.02)];
+        let mut rebuilds = 0;
+        for frame in 0..100 {
+            if cache.refresh(&dawn(1.0 + frame as f32 * 0.002), &cubes, &mut shoot) {
+                rebuilds += 1;
+            }
+        }
+        // el primero cambia el color; de 1.0 a 1.2 se cruza la tolerancia una sola vez
+        assert_eq!(rebuilds, 2);
+        assert!(cache.refresh(&dawn(2.0), &cubes, &mut shoot));
+        assert!(!cache.refresh(&dawn(2.0 * 1.05), &cubes, &mut shoot));
+
+        let mut moved = cubes.clone();
+        moved[0].center.y = 1.0;
+        assert!(cache.refresh(&sun(0.5), &moved, &mut shoot));
+
+        cache.clear();
+        assert!(cache.map().is_none());
+        assert!(cache.refresh(&sun(0.5), &moved, &mut shoot));
+        assert_eq!(shots, 7);
+    }
+}
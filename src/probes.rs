@@ -0,0 +1,229 @@
+// probes.rs
+// grilla de sondas de irradiancia sobre el diorama: cada sonda guarda la luz que le
+// llega (armonicos esfericos) y a que distancia estan las paredes, para que la luz
+// rebotada sea barata al sombrear. Se actualizan unas pocas por cuadro
+use raylib::prelude::*;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::ibl::{sh_basis, sh_irradiance};
+
+// rayos por sonda en cada actualizacion
+const PROBE_RAYS: usize = 32;
+// cuanto pesa una actualizacion nueva contra lo acumulado (suaviza el ruido)
+const HYSTERESIS: f32 = 0.3;
+
+// direcciones de los 6 lados para la distancia a las paredes
+fn axes() -> [Vector3; 6] {
+    [
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(-1.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, -1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(0.0, 0.0, -1.0),
+    ]
+}
+
+#[derive(Clone)]
+struct Probe {
+    position: Vector3,
+    // sondas dentro de un bloque opaco no ven nada util
+    active: bool,
+    // ya tiene al menos una actualizacion
+    ready: bool,
+    sh: [Color; 9],
+    // distancia media y media del cuadrado hacia cada lado (visibilidad de Chebyshev)
+    depth: [(f32, f32); 6],
+}
+
+// resultado de trazar una sonda, para aplicarlo despues sin pedir la grilla mutable
+pub struct ProbeUpdate {
+    index: usize,
+    sh: [Color; 9],
+    depth: [(f32, f32); 6],
+}
+
+pub struct ProbeGrid {
+    min: Vector3,
+    spacing: f32,
+    counts: (usize, usize, usize),
+    probes: Vec<Probe>,
+    // siguiente sonda a actualizar (ronda)
+    next: usize,
+    // giro de las direcciones de los rayos en cada ronda
+    round: u32,
+}
+
+impl ProbeGrid {
+    // sondas cada spacing unidades sobre la caja de los cubos (espacio del diorama)
+    pub fn build(cubes: &[Cube], spacing: f32) -> Self {
+        let mut lo = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut hi = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+        for cube in cubes {
+            let h = cube.size * 0.5;
+            lo = Vector3::new(lo.x.min(cube.center.x - h), lo.y.min(cube.center.y - h), lo.z.min(cube.center.z - h));
+            hi = Vector3::new(hi.x.max(cube.center.x + h), hi.y.max(cube.center.y + h), hi.z.max(cube.center.z + h));
+        }
+        if cubes.is_empty() {
+            lo = Vector3::zero();
+            hi = Vector3::zero();
+        }
+        let count = |a: f32, b: f32| ((b - a) / spacing).ceil().max(0.0) as usize + 1;
+        let counts = (count(lo.x, hi.x), count(lo.y, hi.y), count(lo.z, hi.z));
+
+        let mut probes = Vec::with_capacity(counts.0 * counts.1 * counts.2);
+        for y in 0..counts.1 {
+            for z in 0..counts.2 {
+                for x in 0..counts.0 {
+                    let position = lo + Vector3::new(x as f32, y as f32, z as f32) * spacing;
+                    let inside = cubes.iter().any(|c| {
+                        let h = c.size * 0.5;
+                        c.material.is_opaque()
+                            && (position.x - c.center.x).abs() < h
+                            && (position.y - c.center.y).abs() < h
+                            && (position.z - c.center.z).abs() < h
+                    });
+                    probes.push(Probe {
+                        position,
+                        active: !inside,
+                        ready: false,
+                        sh: [Color::black(); 9],
+                        depth: [(0.0, 0.0); 6],
+                    });
+                }
+            }
+        }
+        ProbeGrid { min: lo, spacing, counts, probes, next: 0, round: 0 }
+    }
+
+    // trazar las proximas count sondas activas. trace(origen, direccion) devuelve la
+    // radiancia que llega por ese rayo y la distancia al primer impacto
+    pub fn trace_batch(&self, count: usize, trace: impl Fn(Vector3, Vector3) -> (Color, f32) + Sync) -> Vec<ProbeUpdate> {
+        let mut indices = Vec::with_capacity(count);
+        let mut i = self.next;
+        for _ in 0..self.probes.len() {
+            if indices.len() == count { break; }
+            if self.probes[i].active {
+                indices.push(i);
+            }
+            i = (i + 1) % self.probes.len();
+        }
+
+        let max_distance = self.spacing * 1.5;
+        let offset = self.round as f32 * 0.618034;
+        indices
+            .into_par_iter()
+            .map(|index| {
+                let probe = &self.probes[index];
+                let mut sh = [Color::black(); 9];
+                let mut depth_sum = [(0.0f32, 0.0f32, 0.0f32); 6];
+                for k in 0..PROBE_RAYS {
+                    let dir = fibonacci_direction(k, PROBE_RAYS, offset);
+                    let (radiance, distance) = trace(probe.position, dir);
+                    for (coeff, basis) in sh.iter_mut().zip(sh_basis(dir)) {
+                        *coeff = *coeff + radiance * (basis * 4.0 * PI / PROBE_RAYS as f32);
+                    }
+                    let d = distance.min(max_distance);
+                    for (sum, axis) in depth_sum.iter_mut().zip(axes()) {
+                        let w = dir.dot(axis).max(0.0).powi(2);
+                        *sum = (sum.0 + w * d, sum.1 + w * d * d, sum.2 + w);
+                    }
+                }
+                let depth = depth_sum.map(|(d, d2, w)| {
+                    if w > 0.0 { (d / w, d2 / w) } else { (max_distance, max_distance * max_distance) }
+                });
+                ProbeUpdate { index, sh, depth }
+            })
+            .collect()
+    }
+
+    pub fn apply(&mut self, updates: Vec<ProbeUpdate>) {
+        for update in updates {
+            let probe = &mut self.probes[update.index];
+            let alpha = if probe.ready { HYSTERESIS } else { 1.0 };
+            for (old, new) in probe.sh.iter_mut().zip(update.sh) {
+                *old = *old * (1.0 - alpha) + new * alpha;
+            }
+            for (old, new) in probe.depth.iter_mut().zip(update.depth) {
+                *old = (old.0 * (1.0 - alpha) + new.0 * alpha, old.1 * (1.0 - alpha) + new.1 * alpha);
+            }
+            probe.ready = true;
+            self.next = (update.index + 1) % self.probes.len();
+        }
+        self.round = self.round.wrapping_add(1);
+    }
+
+    // irradiancia (radiancia difusa para albedo 1) en un punto con su normal, mezclando
+    // las 8 sondas vecinas: trilinear, sin las que quedan detras de la superficie y
+    // sin las que tienen una pared en el medio. None si ninguna sirve
+    pub fn irradiance(&self, point: Vector3, normal: Vector3) -> Option<Color> {
+        // un poco afuera de la superficie para no pelear con la pared donde esta
+        let p = point + normal * (self.spacing * 0.1);
+        let g = (p - self.min) / self.spacing;
+        let base = (g.x.floor(), g.y.floor(), g.z.floor());
+        let f = (g.x - base.0, g.y - base.1, g.z - base.2);
+
+        let mut sum = Color::black();
+        let mut total = 0.0;
+        for corner in 0..8 {
+            let (ox, oy, oz) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let (x, y, z) = (base.0 as i64 + ox, base.1 as i64 + oy, base.2 as i64 + oz);
+            if x < 0 || y < 0 || z < 0 { continue; }
+            let (x, y, z) = (x as usize, y as usize, z as usize);
+            if x >= self.counts.0 || y >= self.counts.1 || z >= self.counts.2 { continue; }
+            let probe = &self.probes[(y * self.counts.2 + z) * self.counts.0 + x];
+            if !probe.active || !probe.ready { continue; }
+
+            let trilinear = (if ox == 1 { f.0 } else { 1.0 - f.0 })
+                * (if oy == 1 { f.1 } else { 1.0 - f.1 })
+                * (if oz == 1 { f.2 } else { 1.0 - f.2 });
+            let to_probe = probe.position - p;
+            let dist = to_probe.length().max(1e-4);
+            let dir = to_probe / dist;
+
+            // sondas detras de la superficie pesan poco (envuelto, nunca 0)
+            let facing = (dir.dot(normal) + 1.0) * 0.5;
+            let mut weight = facing * facing + 0.2;
+
+            // visibilidad: la sonda mira hacia el punto (-dir) y sabe a que distancia
+            // suele chocar; si el punto esta mas lejos probablemente hay una pared
+            let (mean, mean2) = probe.depth_toward(-dir);
+            if dist > mean {
+                let variance = (mean2 - mean * mean).abs().max(1e-4);
+                let chebyshev = variance / (variance + (dist - mean) * (dist - mean));
+                weight *= chebyshev.powi(3).max(0.05);
+            }
+
+            let w = (trilinear * weight).max(1e-6);
+            sum = sum + sh_irradiance(&probe.sh, normal) * w;
+            total += w;
+        }
+        if total <= 0.0 { None } else { Some(sum * (1.0 / total)) }
+    }
+}
+
+impl Probe {
+    fn depth_toward(&self, dir: Vector3) -> (f32, f32) {
+        let mut mean = 0.0;
+        let mut mean2 = 0.0;
+        let mut total = 0.0;
+        for (&(d, d2), axis) in self.depth.iter().zip(axes()) {
+            let w = dir.dot(axis).max(0.0).powi(2);
+            mean += d * w;
+            mean2 += d2 * w;
+            total += w;
+        }
+        if total <= 0.0 { (0.0, 0.0) } else { (mean / total, mean2 / total) }
+    }
+}
+
+// k-esima de n direcciones repartidas parejo en la esfera (espiral de Fibonacci),
+// giradas por offset para que cada ronda mire entre los rayos de la anterior
+fn fibonacci_direction(k: usize, n: usize, offset: f32) -> Vector3 {
+    let y = 1.0 - 2.0 * (k as f32 + 0.5) / n as f32;
+    let r = (1.0 - y * y).max(0.0).sqrt();
+    let phi = 2.0 * PI * ((k as f32 * 0.618034 + offset) % 1.0);
+    Vector3::new(r * phi.cos(), y, r * phi.sin())
+}
@@ -1,13 +1,45 @@
 use raylib::prelude::*;
 
+// rayos vecinos (un pixel a la derecha y uno abajo) para saber
+// cuanto mide el pixel de pantalla en cada impacto
+#[derive(Debug, Clone, Copy)]
+pub struct RayDifferential {
+    pub rx_origin: Vector3,
+    pub rx_direction: Vector3,
+    pub ry_origin: Vector3,
+    pub ry_direction: Vector3,
+}
+
+impl RayDifferential {
+    // desplazamientos donde los rayos vecinos cortan el plano tangente (p, n)
+    pub fn hit_offsets(&self, p: Vector3, n: Vector3) -> Option<(Vector3, Vector3)> {
+        let dx = n.dot(self.rx_direction);
+        let dy = n.dot(self.ry_direction);
+        if dx.abs() < 1e-6 || dy.abs() < 1e-6 {
+            return None;
+        }
+        let tx = n.dot(p - self.rx_origin) / dx;
+        let ty = n.dot(p - self.ry_origin) / dy;
+        let px = self.rx_origin + self.rx_direction * tx;
+        let py = self.ry_origin + self.ry_direction * ty;
+        Some((px - p, py - p))
+    }
+}
+
 pub struct Ray {
     pub origin: Vector3,
     pub direction: Vector3,
+    pub differential: Option<RayDifferential>,
 }
 
 impl Ray {
     pub fn new(origin: Vector3, direction: Vector3) -> Self {
-        Ray { origin, direction: direction.normalized() }
+        Ray { origin, direction: direction.normalized(), differential: None }
+    }
+
+    pub fn with_differential(mut self, differential: Option<RayDifferential>) -> Self {
+        self.differential = differential;
+        self
     }
 
     pub fn at(&self, t: f32) -> Vector3 {
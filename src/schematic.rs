@@ -0,0 +1,357 @@
+// schematic.rs
+// importador de construcciones de Minecraft: Sponge .schem (v1-v3) y estructuras vanilla .nbt
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::nbt::{self, Tag};
+
+// tabla de mapeo id de bloque -> material
+// None significa "no generar cubo" (aire, bloques de estructura)
+pub struct BlockMapping {
+    exact: HashMap<String, Option<Material>>,
+    // reglas por subcadena, se prueban en orden
+    keywords: Vec<(String, Option<Material>)>,
+    pub fallback: Option<Material>,
+}
+
+impl BlockMapping {
+    pub fn new() -> Self {
+        let mut mapping = BlockMapping {
+            exact: HashMap::new(),
+            keywords: Vec::new(),
+            fallback: Some(Material::stone()),
+        };
+
+        for air in ["air", "cave_air", "void_air", "structure_void", "barrier", "light"] {
+            mapping.set(air, None);
+        }
+        mapping.set("grass_block", Some(Material::grass()));
+        mapping.set("dirt", Some(Material::dirt()));
+        mapping.set("coarse_dirt", Some(Material::dirt()));
+        mapping.set("rooted_dirt", Some(Material::dirt()));
+        mapping.set("fire", Some(Material::fire()));
+        mapping.set("soul_fire", Some(Material::fire()));
+        mapping.set("glowstone", Some(Material::lamp()));
+        mapping.set("sea_lantern", Some(Material::lamp()));
+        mapping.set("stone_bricks", Some(Material::stone()));
+        // plantas: no son bloques llenos ("grass" es el id viejo del pasto corto)
+        for plant in ["grass", "short_grass", "tall_grass", "seagrass", "tall_seagrass", "fern", "large_fern", "dead_bush"] {
+            mapping.set(plant, None);
+        }
+        // tallos de hongos del Nether: son troncos ("stem" solo atraparia tambien las plantas
+        // pumpkin_stem y melon_stem)
+        for stem in ["crimson_stem", "warped_stem", "stripped_crimson_stem", "stripped_warped_stem"] {
+            mapping.set(stem, Some(Material::wood()));
+        }
+        for plant in ["pumpkin_stem", "melon_stem", "attached_pumpkin_stem", "attached_melon_stem"] {
+            mapping.set(plant, None);
+        }
+
+        // el orden importa: "glass" antes que "stone" para no atrapar glass con stone
+        mapping.add_keyword("glass", Some(Material::glass()));
+        mapping.add_keyword("water", Some(Material::water()));
+        mapping.add_keyword("leaves", Some(Material::leaves()));
+        mapping.add_keyword("torch", Some(Material::torch()));
+        mapping.add_keyword("lantern", Some(Material::torch()));
+        mapping.add_keyword("campfire", Some(Material::fire()));
+        mapping.add_keyword("lava", Some(Material::fire()));
+        mapping.add_keyword("brick", Some(Material::brick()));
+        mapping.add_keyword("planks", Some(Material::woodhouse()));
+        mapping.add_keyword("log", Some(Material::wood()));
+        mapping.add_keyword("_wood", Some(Material::wood()));
+        mapping.add_keyword("stone", Some(Material::stone()));
+        mapping.add_keyword("andesite", Some(Material::stone()));
+        mapping.add_keyword("diorite", Some(Material::stone()));
+        mapping.add_keyword("granite", Some(Material::stone()));
+        mapping.add_keyword("deepslate", Some(Material::stone()));
+        mapping.add_keyword("gravel", Some(Material::stone()));
+        mapping.add_keyword("moss", Some(Material::grass()));
+
+        mapping
+    }
+
+    // leer reglas extra desde un archivo de texto
+    // formato por linea: `minecraft:oak_log = wood`, `*glass* = glass`, `*default* = stone`
+    // el lado derecho es un preset de Material o `none`
+    pub fn from_file(path: &str) -> Result<Self> {
+        let mut mapping = BlockMapping::new();
+        let content = std::fs::read_to_string(path)?;
+        for (line_no, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(Error::new(ErrorKind::InvalidData, format!("{}:{}: falta '='", path, line_no + 1)));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let material = if value == "none" {
+                None
+            } else {
+                match Material::from_preset(value) {
+                    Some(m) => Some(m),
+                    None => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!("{}:{}: material desconocido '{}'", path, line_no + 1, value),
+                        ));
+                    }
+                }
+            };
+
+            if key == "*default*" {
+                mapping.fallback = material;
+            } else if key.len() > 2 && key.starts_with('*') && key.ends_with('*') {
+                // las reglas del archivo tienen prioridad sobre las de fabrica
+                mapping.keywords.insert(0, (key[1..key.len() - 1].to_string(), material));
+            } else {
+                mapping.set(key, material);
+            }
+        }
+        Ok(mapping)
+    }
+
+    // regla exacta, acepta ids con o sin "minecraft:"
+    pub fn set(&mut self, block_id: &str, material: Option<Material>) {
+        self.exact.insert(strip_namespace(block_id).to_string(), material);
+    }
+
+    pub fn add_keyword(&mut self, keyword: &str, material: Option<Material>) {
+        self.keywords.push((keyword.to_string(), material));
+    }
+
+    // resolver un id como "minecraft:oak_log[axis=y]"
+    pub fn material_for(&self, block_state: &str) -> Option<Material> {
+        let id = strip_namespace(block_state.split('[').next().unwrap_or(block_state));
+        if let Some(m) = self.exact.get(id) {
+            return m.clone();
+        }
+        for (keyword, m) in &self.keywords {
+            if id.contains(keyword.as_str()) {
+                return m.clone();
+            }
+        }
+        self.fallback.clone()
+    }
+}
+
+fn strip_namespace(id: &str) -> &str {
+    id.strip_prefix("minecraft:").unwrap_or(id)
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+// bloques ya decodificados: tamaño + (x, y, z, estado)
+struct BlockGrid {
+    size: (i32, i32, i32),
+    blocks: Vec<(i32, i32, i32, String)>,
+}
+
+// cargar una construccion (.schem o .nbt) y convertir cada bloque en un cubo
+// position: centro de la base de la construccion, scale: tamaño de cada bloque
+pub fn load_schematic(path: &str, position: Vector3, scale: f32, mapping: &BlockMapping) -> Result<Vec<Cube>> {
+    let root = nbt::read_file(path)?;
+    let grid = if root.get("blocks").is_some() && root.get("size").is_some() {
+        read_structure(&root)?
+    } else {
+        // Sponge v3 envuelve todo en un compound "Schematic"
+        let schem = root.get("Schematic").filter(|s| s.get("Width").is_some()).unwrap_or(&root);
+        read_sponge(schem)?
+    };
+    Ok(grid_to_cubes(&grid, position, scale, mapping))
+}
+
+// estructura vanilla (structure block): size, palette/palettes, blocks
+fn read_structure(root: &Tag) -> Result<BlockGrid> {
+    let size = root.get("size").and_then(|t| t.as_int_vec()).filter(|v| v.len() == 3)
+        .ok_or_else(|| invalid("size invalido"))?;
+    let palette_tag = root.get("palette")
+        .or_else(|| root.get("palettes").and_then(|p| p.as_list()).and_then(|l| l.first()))
+        .and_then(|p| p.as_list())
+        .ok_or_else(|| invalid("falta palette"))?;
+    let palette: Vec<String> = palette_tag.iter()
+        .map(|entry| entry.get("Name").and_then(|n| n.as_str()).unwrap_or("minecraft:air").to_string())
+        .collect();
+
+    let mut blocks = Vec::new();
+    for block in root.get("blocks").and_then(|b| b.as_list()).unwrap_or(&[]) {
+        let state = block.get("state").and_then(|s| s.as_i64()).unwrap_or(-1);
+        let pos = block.get("pos").and_then(|p| p.as_int_vec()).unwrap_or_default();
+        if pos.len() != 3 || state < 0 || state as usize >= palette.len() { continue; }
+        blocks.push((pos[0], pos[1], pos[2], palette[state as usize].clone()));
+    }
+
+    Ok(BlockGrid { size: (size[0], size[1], size[2]), blocks })
+}
+
+// Sponge schematic: Palette + BlockData (v1/v2) o Blocks { Palette, Data } (v3)
+fn read_sponge(schem: &Tag) -> Result<BlockGrid> {
+    let dim = |key: &str| schem.get(key).and_then(|t| t.as_i64()).map(|v| v as u16 as i32);
+    let (w, h, l) = match (dim("Width"), dim("Height"), dim("Length")) {
+        (Some(w), Some(h), Some(l)) => (w, h, l),
+        _ => return Err(invalid("schematic sin Width/Height/Length")),
+    };
+
+    let container = schem.get("Blocks").unwrap_or(schem);
+    let palette_tag = container.get("Palette").and_then(|p| p.as_compound())
+        .ok_or_else(|| invalid("schematic sin Palette"))?;
+    let data = match container.get("Data").or_else(|| container.get("BlockData")) {
+        Some(Tag::ByteArray(bytes)) => bytes,
+        _ => return Err(invalid("schematic sin BlockData")),
+    };
+
+    // indice de paleta -> estado del bloque
+    let mut palette: HashMap<i64, String> = HashMap::new();
+    for (state, index) in palette_tag {
+        if let Some(i) = index.as_i64() {
+            palette.insert(i, state.clone());
+        }
+    }
+
+    // BlockData son varints, en orden x + z * w + y * w * l
+    let mut blocks = Vec::new();
+    let mut index = 0i64;
+    let mut pos = 0usize;
+    while pos < data.len() && index < (w as i64) * (h as i64) * (l as i64) {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = *data.get(pos).ok_or_else(|| invalid("varint truncado"))? as u8;
+            pos += 1;
+            value |= ((byte & 0x7f) as i64) << shift;
+            if byte & 0x80 == 0 { break; }
+            shift += 7;
+            if shift > 35 { return Err(invalid("varint demasiado largo")); }
+        }
+        if let Some(state) = palette.get(&value) {
+            let x = (index % w as i64) as i32;
+            let z = ((index / w as i64) % l as i64) as i32;
+            let y = (index / (w as i64 * l as i64)) as i32;
+            blocks.push((x, y, z, state.clone()));
+        }
+        index += 1;
+    }
+
+    Ok(BlockGrid { size: (w, h, l), blocks })
+}
+
+fn grid_to_cubes(grid: &BlockGrid, position: Vector3, scale: f32, mapping: &BlockMapping) -> Vec<Cube> {
+    let mut resolved: HashMap<&str, Option<Material>> = HashMap::new();
+    let mut solid: HashMap<(i32, i32, i32), Material> = HashMap::new();
+    for (x, y, z, state) in &grid.blocks {
+        let material = resolved
+            .entry(state.as_str())
+            .or_insert_with(|| mapping.material_for(state))
+            .clone();
+        if let Some(m) = material {
+            solid.insert((*x, *y, *z), m);
+        }
+    }
+
+    // un bloque rodeado por 6 vecinos opacos nunca se ve
+    let opaque = |p: &(i32, i32, i32)| solid.get(p).map(|m| m.is_opaque() && m.emission.is_none()).unwrap_or(false);
+    let half_w = grid.size.0 as f32 / 2.0;
+    let half_l = grid.size.2 as f32 / 2.0;
+
+    let mut cubes = Vec::new();
+    for (x, y, z, _) in &grid.blocks {
+        let (x, y, z) = (*x, *y, *z);
+        let Some(material) = solid.get(&(x, y, z)) else { continue };
+        let hidden = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)]
+            .iter()
+            .all(|(dx, dy, dz)| opaque(&(x + dx, y + dy, z + dz)));
+        if hidden { continue; }
+        let local = Vector3::new(x as f32 + 0.5 - half_w, y as f32 + 0.5, z as f32 + 0.5 - half_l);
+        cubes.push(Cube::new(position + local * scale, scale, material.clone()));
+    }
+    cubes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{assert_cube, gzip, TempFile};
+    use crate::nbt::encode;
+
+    fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(entries.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    #[test]
+    fn plants_are_not_grass_blocks() {
+        let mapping = BlockMapping::new();
+        let grass = mapping.material_for("minecraft:grass_block[snowy=false]").unwrap();
+        assert_eq!(grass.texture_name, Material::grass().texture_name);
+        for plant in ["minecraft:short_grass", "minecraft:tall_grass[half=upper]", "minecraft:seagrass", "minecraft:grass"] {
+            assert!(mapping.material_for(plant).is_none(), "{}", plant);
+        }
+        assert_eq!(mapping.material_for("minecraft:oak_log[axis=y]").unwrap().texture_name, Material::wood().texture_name);
+        assert_eq!(mapping.material_for("minecraft:white_stained_glass").unwrap().texture_name, Material::glass().texture_name);
+    }
+
+    #[test]
+    fn dirt_and_stems_map_to_their_own_materials() {
+        let mapping = BlockMapping::new();
+        let dirt = mapping.material_for("minecraft:dirt").unwrap();
+        assert_eq!(dirt.texture_name, Material::dirt().texture_name);
+        assert!(dirt.face_textures.is_none());
+        assert_eq!(mapping.material_for("minecraft:warped_stem[axis=y]").unwrap().texture_name, Material::wood().texture_name);
+        for plant in ["minecraft:pumpkin_stem[age=3]", "minecraft:attached_melon_stem[facing=north]"] {
+            assert!(mapping.material_for(plant).is_none(), "{}", plant);
+        }
+    }
+
+    #[test]
+    fn structure_nbt_round_trip() {
+        let palette = Tag::List(vec![
+            compound(vec![("Name", Tag::String("minecraft:air".into()))]),
+            compound(vec![("Name", Tag::String("minecraft:oak_planks".into()))]),
+        ]);
+        let block = |pos: [i32; 3], state: i32| {
+            compound(vec![("pos", Tag::List(pos.iter().map(|&v| Tag::Int(v)).collect())), ("state", Tag::Int(state))])
+        };
+        let root = compound(vec![
+            ("size", Tag::List(vec![Tag::Int(2), Tag::Int(1), Tag::Int(1)])),
+            ("palette", palette),
+            ("blocks", Tag::List(vec![block([0, 0, 0], 1), block([1, 0, 0], 0)])),
+        ]);
+        // las estructuras vanilla vienen con gzip
+        let file = TempFile::new("structure.nbt", &gzip(&encode(&root)));
+
+        let cubes = load_schematic(&file.path, Vector3::new(0.0, 0.0, 0.0), 0.5, &BlockMapping::new()).unwrap();
+        assert_eq!(cubes.len(), 1);
+        assert_eq!(cubes[0].material.texture_name, Material::woodhouse().texture_name);
+        // centrado en X/Z sobre position, apoyado en Y
+        assert_cube(&cubes[0], [-0.25, 0.25, 0.0], 0.5);
+    }
+
+    #[test]
+    fn sponge_schematic_reads_varint_block_data() {
+        // 200 entradas en la paleta para que el indice 130 ocupe dos bytes de varint
+        let mut palette = vec![("minecraft:air".to_string(), Tag::Int(0))];
+        for i in 1..200 {
+            palette.push((format!("minecraft:filler_{}", i), Tag::Int(i)));
+        }
+        palette[130] = ("minecraft:bricks".to_string(), Tag::Int(130));
+        let palette = Tag::Compound(palette.into_iter().collect());
+        // 1x2x1: abajo ladrillo (130 = 0x82 0x01), arriba aire
+        let data = Tag::ByteArray(vec![0x82u8 as i8, 0x01, 0x00]);
+        let root = compound(vec![(
+            "Schematic",
+            compound(vec![
+                ("Width", Tag::Short(1)),
+                ("Height", Tag::Short(2)),
+                ("Length", Tag::Short(1)),
+                ("Blocks", compound(vec![("Palette", palette), ("Data", data)])),
+            ]),
+        )]);
+        let file = TempFile::new("v3.schem", &encode(&root));
+
+        let cubes = load_schematic(&file.path, Vector3::new(1.0, 2.0, 3.0), 1.0, &BlockMapping::new()).unwrap();
+        assert_eq!(cubes.len(), 1);
+        assert_eq!(cubes[0].material.texture_name, Material::brick().texture_name);
+        assert_cube(&cubes[0], [1.0, 2.5, 3.0], 1.0);
+    }
+}
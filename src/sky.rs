@@ -0,0 +1,267 @@
+// sky.rs
+// cielo analitico de Preetham, Shirley y Smits (1999) segun la direccion del sol,
+// con disco solar, colores de atardecer y transicion al cielo nocturno con luna y estrellas
+use raylib::prelude::*;
+use crate::color::Color;
+
+// el modelo da luminancia en kcd/m2; esto deja el cenit de mediodia cerca de 0.4
+const SKY_SCALE: f32 = 0.05;
+// radio angular del disco (el doble del real para que se vea a esta resolucion)
+const SUN_RADIUS: f32 = 0.02;
+const SUN_RADIANCE: f32 = 40.0;
+// profundidad optica de Rayleigh en R, G, B; el aerosol se suma segun la turbidez
+const RAYLEIGH_DEPTH: [f32; 3] = [0.04, 0.097, 0.24];
+// cielo nocturno (lineal)
+const NIGHT_ZENITH: Color = Color { r: 0.004, g: 0.005, b: 0.012 };
+const NIGHT_HORIZON: Color = Color { r: 0.012, g: 0.014, b: 0.025 };
+// luna: opuesta al sol, un poco mas grande que el sol en pantalla
+const MOON_RADIUS: f32 = 0.03;
+const MOON_RADIANCE: f32 = 1.5;
+const MOON_COLOR: Color = Color { r: 0.75, g: 0.85, b: 1.0 };
+// estrellas: celdas por cara de un cubo alrededor del observador
+const STAR_GRID: f32 = 180.0;
+const STAR_DENSITY: f32 = 0.08; // fraccion de celdas con estrella
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// funcion de distribucion de Perez
+fn perez(c: &[f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    (1.0 + c[0] * (c[1] / cos_theta).exp()) * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+}
+
+pub struct PreethamSky {
+    turbidity: f32,
+    sun_direction: Vector3,
+    // coeficientes de Perez para Y, x, y
+    coeffs: [[f32; 5]; 3],
+    // Y, x, y en el cenit dividido por F(0, theta_sol)
+    zenith: [f32; 3],
+    // 0 = luna nueva, 0.5 = llena
+    moon_phase: f32,
+    // segundos, para el titileo de las estrellas
+    time: f32,
+}
+
+impl PreethamSky {
+    pub fn new(turbidity: f32, sun_direction: Vector3) -> Self {
+        let mut sky = PreethamSky {
+            turbidity,
+            sun_direction: sun_direction.normalized(),
+            coeffs: [[0.0; 5]; 3],
+            zenith: [0.0; 3],
+            moon_phase: 0.5,
+            time: 0.0,
+        };
+        sky.update();
+        sky
+    }
+
+    pub fn sun_direction(&self) -> Vector3 {
+        self.sun_direction
+    }
+
+    pub fn set_sun_direction(&mut self, direction: Vector3) {
+        self.sun_direction = direction.normalized();
+        self.update();
+    }
+
+    pub fn set_moon_phase(&mut self, phase: f32) {
+        self.moon_phase = phase.rem_euclid(1.0);
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    pub fn moon_direction(&self) -> Vector3 {
+        -self.sun_direction
+    }
+
+    fn update(&mut self) {
+        let t = self.turbidity;
+        self.coeffs = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        // el modelo no sirve con el sol bajo el horizonte: se queda en el atardecer
+        // y la noche se mezcla encima
+        let theta_s = self.sun_direction.y.clamp(0.02, 1.0).acos();
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+        let y_zenith = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let (t2, th2, th3) = (t * t, theta_s * theta_s, theta_s * theta_s * theta_s);
+        let x_zenith = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * theta_s)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * theta_s + 0.25886);
+        let yc_zenith = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * theta_s)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * theta_s + 0.26688);
+
+        let zenith = [y_zenith, x_zenith, yc_zenith];
+        for ((out, value), c) in self.zenith.iter_mut().zip(zenith).zip(&self.coeffs) {
+            *out = value / perez(c, 1.0, theta_s);
+        }
+    }
+
+    // 0 de noche, 1 de dia; la transicion dura el crepusculo
+    pub fn day_factor(&self) -> f32 {
+        smoothstep(-0.15, 0.05, self.sun_direction.y)
+    }
+
+    // cielo sin el disco solar (lo que se usa para la luz ambiental)
+    pub fn radiance(&self, direction: &Vector3) -> Color {
+        let d = direction.normalized();
+        // bajo el horizonte: el horizonte oscurecido (suelo lejano)
+        let ground = smoothstep(0.0, -0.1, d.y);
+        let up = Vector3::new(d.x, d.y.max(0.01), d.z).normalized();
+
+        let day = self.day_factor();
+        let mut color = Color::black();
+        if day > 0.0 {
+            let sun = Vector3::new(self.sun_direction.x, self.sun_direction.y.max(0.02), self.sun_direction.z).normalized();
+            let gamma = up.dot(sun).clamp(-1.0, 1.0).acos();
+            let xyy: [f32; 3] = std::array::from_fn(|i| self.zenith[i] * perez(&self.coeffs[i], up.y, gamma));
+            color = xyy_to_rgb(xyy[0] * SKY_SCALE, xyy[1], xyy[2]) * day;
+        }
+        let night = NIGHT_HORIZON * (1.0 - up.y) + NIGHT_ZENITH * up.y;
+        color = color + night * (1.0 - day);
+        color * (1.0 - 0.7 * ground)
+    }
+
+    // disco solar con oscurecimiento hacia el borde
+    pub fn sun_disc(&self, direction: &Vector3) -> Color {
+        let cos_angle = direction.normalized().dot(self.sun_direction);
+        let angle = cos_angle.clamp(-1.0, 1.0).acos();
+        if angle >= SUN_RADIUS || direction.y < 0.0 {
+            return Color::black();
+        }
+        let limb = 1.0 - 0.6 * (angle / SUN_RADIUS).powi(2);
+        self.sun_color() * (SUN_RADIANCE * limb)
+    }
+
+    // disco lunar: la parte iluminada depende de la fase (terminador sobre la esfera)
+    pub fn moon_disc(&self, direction: &Vector3) -> Color {
+        let d = direction.normalized();
+        let moon = self.moon_direction();
+        let angle = d.dot(moon).clamp(-1.0, 1.0).acos();
+        if angle >= MOON_RADIUS || d.y < 0.0 {
+            return Color::black();
+        }
+
+        // punto de la esfera lunar visto de frente (z hacia el observador)
+        let (tangent, bitangent) = basis(moon);
+        let offset = d - moon * d.dot(moon);
+        let scale = MOON_RADIUS.sin();
+        let x = offset.dot(tangent) / scale;
+        let y = offset.dot(bitangent) / scale;
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+
+        // luz del sol vista desde la luna: de atras en luna nueva, de frente en llena
+        let phi = self.moon_phase * std::f32::consts::TAU;
+        let lit = smoothstep(-0.05, 0.05, x * phi.sin() - z * phi.cos());
+        let earthshine = 0.03;
+        MOON_COLOR * (MOON_RADIANCE * (lit + earthshine))
+    }
+
+    // luz de luna: proporcional a la parte iluminada y apagada bajo el horizonte
+    pub fn moon_color(&self) -> Color {
+        let illuminated = (1.0 - (self.moon_phase * std::f32::consts::TAU).cos()) * 0.5;
+        let visible = smoothstep(-0.03, 0.05, self.moon_direction().y);
+        MOON_COLOR * (illuminated * visible)
+    }
+
+    // campo de estrellas con titileo, solo de noche
+    pub fn stars(&self, direction: &Vector3) -> Color {
+        let d = direction.normalized();
+        let night = 1.0 - self.day_factor();
+        if night <= 0.0 || d.y <= 0.0 {
+            return Color::black();
+        }
+
+        // celda en la cara del cubo que contiene la direccion
+        let (ax, ay, az) = (d.x.abs(), d.y.abs(), d.z.abs());
+        let (face, u, v) = if ax >= ay && ax >= az {
+            (if d.x > 0.0 { 0.0 } else { 1.0 }, d.z / ax, d.y / ax)
+        } else if ay >= az {
+            (if d.y > 0.0 { 2.0 } else { 3.0 }, d.x / ay, d.z / ay)
+        } else {
+            (if d.z > 0.0 { 4.0 } else { 5.0 }, d.x / az, d.y / az)
+        };
+        let gx = (u * 0.5 + 0.5) * STAR_GRID;
+        let gy = (v * 0.5 + 0.5) * STAR_GRID;
+        let (cx, cy) = (gx.floor(), gy.floor());
+        let h = hash3(face, cx, cy);
+        if h > STAR_DENSITY {
+            return Color::black();
+        }
+
+        // posicion, brillo y color de la estrella dentro de su celda
+        let sx = cx + 0.2 + 0.6 * hash3(cx, cy, face + 10.0);
+        let sy = cy + 0.2 + 0.6 * hash3(cy, face + 20.0, cx);
+        let dist = (gx - sx).hypot(gy - sy);
+        if dist > 0.4 {
+            return Color::black();
+        }
+        let falloff = 1.0 - dist / 0.4;
+        let brightness = 0.05 + 0.6 * (h / STAR_DENSITY).powi(4);
+        let twinkle_speed = 2.0 + 4.0 * hash3(face, cy, cx);
+        let twinkle = 0.75 + 0.25 * (self.time * twinkle_speed + h * 97.0).sin();
+        let warm = hash3(cx + 5.0, cy, face);
+        let tint = Color::new(0.8 + 0.2 * warm, 0.85, 1.0 - 0.2 * warm);
+        // se apagan cerca del horizonte (extincion atmosferica)
+        let extinction = smoothstep(0.0, 0.15, d.y);
+        tint * (brightness * twinkle * falloff * night * extinction)
+    }
+
+    // 0 bajo el horizonte, 1 con el sol entero arriba
+    pub fn sun_visibility(&self) -> f32 {
+        smoothstep(-0.03, 0.03, self.sun_direction.y)
+    }
+
+    // luz del sol que atraviesa la atmosfera: blanca al mediodia, naranja al atardecer
+    pub fn sun_color(&self) -> Color {
+        let elevation = self.sun_direction.y;
+        let visible = self.sun_visibility();
+        if visible <= 0.0 {
+            return Color::black();
+        }
+        // masa de aire de Kasten y Young, relativa al cenit
+        let zenith_deg = elevation.clamp(-1.0, 1.0).acos().to_degrees().min(90.0);
+        let air_mass = 1.0 / (zenith_deg.to_radians().cos() + 0.50572 * (96.07995 - zenith_deg).powf(-1.6364));
+        let aerosol = 0.04 * self.turbidity;
+        let transmit = |i: usize| (-(RAYLEIGH_DEPTH[i] + aerosol) * (air_mass - 1.0)).exp();
+        Color::new(transmit(0), transmit(1), transmit(2)) * visible
+    }
+}
+
+// base ortonormal alrededor de una direccion (tangente horizontal si se puede)
+fn basis(n: Vector3) -> (Vector3, Vector3) {
+    let helper = if n.y.abs() < 0.99 { Vector3::new(0.0, 1.0, 0.0) } else { Vector3::new(1.0, 0.0, 0.0) };
+    let tangent = helper.cross(n).normalized();
+    (tangent, n.cross(tangent))
+}
+
+// numero pseudoaleatorio en [0, 1) a partir de tres valores
+fn hash3(a: f32, b: f32, c: f32) -> f32 {
+    let h = (a * 127.1 + b * 311.7 + c * 74.7).sin() * 43758.547;
+    h - h.floor()
+}
+
+// xyY (CIE) a sRGB lineal
+fn xyy_to_rgb(luminance: f32, x: f32, y: f32) -> Color {
+    if y <= 0.0 {
+        return Color::black();
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
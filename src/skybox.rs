@@ -1,8 +1,53 @@
 use raylib::prelude::*;
+use std::f32::consts::PI;
 use crate::color::Color;
-use crate::texture::Texture;
+use crate::ibl::{direction_to_latlong, Ibl};
+use crate::sky::PreethamSky;
+use crate::texture::{ColorSpace, Texture};
+
+// mapa de entorno equirectangular (lat-long), normalmente HDR
+// u = longitud (centro de la imagen hacia -Z), v = 0 arriba
+pub struct EnvironmentMap {
+    texture: Texture,
+    pub rotation: f32,  // radianes alrededor de Y
+    pub intensity: f32, // multiplicador de la radiancia
+}
+
+impl EnvironmentMap {
+    pub fn load(path: &str, rotation: f32, intensity: f32) -> Result<Self, image::ImageError> {
+        let texture = Texture::load(path, ColorSpace::Srgb)?;
+        Ok(EnvironmentMap { texture, rotation, intensity })
+    }
+
+    pub fn get_color(&self, direction: &Vector3) -> Color {
+        let (u, v) = direction_to_latlong(direction.normalized());
+        let u = u + self.rotation / (2.0 * PI);
+        // sin mezclar el polo norte con el sur al filtrar
+        let half_texel = 0.5 / self.texture.height as f32;
+        let v = v.clamp(half_texel, 1.0 - half_texel);
+        self.texture.get_pixel_bilinear(u, v) * self.intensity
+    }
+}
+
+// hacia donde gira el cielo con textura (entorno, cubemap) y las estrellas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkyOrientation {
+    Fixed,   // quieto respecto a la camara
+    World,   // gira con el diorama (world_angle)
+    SunPath, // sigue el azimut del sol
+}
 
 pub struct Skybox {
+    // entorno lat-long: si esta, reemplaza al cubemap
+    pub environment: Option<EnvironmentMap>,
+    // cielo procedural que sigue al sol; si esta activo reemplaza al cubemap
+    pub procedural: bool,
+    sky: PreethamSky,
+    pub orientation: SkyOrientation,
+    // giro extra en rad/s (nubes que se mueven)
+    pub spin: f32,
+    // giro actual alrededor de Y
+    yaw: f32,
     // gradiente de respaldo
     day_color: Color,
     night_color: Color,
@@ -14,16 +59,28 @@ pub struct Skybox {
     bottom: Option<Texture>,
     front: Option<Texture>,
     back: Option<Texture>,
+    // luz ambiental derivada del cielo actual, guardada en el marco del cielo sin girar
+    ibl: Ibl,
+    // direccion del sol cuando se calculo ibl
+    ibl_sun: Vector3,
 }
 
+// recalcular la luz ambiental cuando el sol se movio mas que esto (radianes)
+const IBL_STEP: f32 = 0.05;
+
 impl Skybox {
     pub fn new(_rl: &mut RaylibHandle, _thread: &RaylibThread) -> Self {
-        let right = Texture::load("assets/skybox/right.png").ok();
-        let left = Texture::load("assets/skybox/left.png").ok();
-        let top = Texture::load("assets/skybox/top.png").ok();
-        let bottom = Texture::load("assets/skybox/bottom.png").ok();
-        let front = Texture::load("assets/skybox/front.png").ok();
-        let back = Texture::load("assets/skybox/back.png").ok();
+        // cada cara puede venir en HDR (right.exr / right.hdr) en lugar del PNG
+        let load_face = |path: &str| {
+            let hdr = Texture::hdr_variant(path);
+            Texture::load(hdr.as_deref().unwrap_or(path), ColorSpace::Srgb).ok()
+        };
+        let right = load_face("assets/skybox/right.png");
+        let left = load_face("assets/skybox/left.png");
+        let top = load_face("assets/skybox/top.png");
+        let bottom = load_face("assets/skybox/bottom.png");
+        let front = load_face("assets/skybox/front.png");
+        let back = load_face("assets/skybox/back.png");
 
         if right.is_some() && left.is_some() && top.is_some() && bottom.is_some() && front.is_some() && back.is_some() {
             println!(" Skybox texturas cargaron bn");
@@ -31,20 +88,155 @@ impl Skybox {
             eprintln!("Skybox texturas no cargaron");
         }
 
-        Skybox {
-            day_color: Color::new(0.5, 0.7, 1.0),
-            night_color: Color::new(0.05, 0.05, 0.15),
-            horizon_color: Color::new(0.8, 0.6, 0.4),
+        // el gradiente se eligio en sRGB; se guarda en lineal como las texturas
+        let mut skybox = Skybox {
+            environment: None,
+            procedural: false,
+            sky: PreethamSky::new(2.5, Vector3::new(0.0, 1.0, 0.0)),
+            orientation: SkyOrientation::World,
+            spin: 0.0,
+            yaw: 0.0,
+            day_color: Color::from_srgb(0.5, 0.7, 1.0),
+            night_color: Color::from_srgb(0.05, 0.05, 0.15),
+            horizon_color: Color::from_srgb(0.8, 0.6, 0.4),
             right,
             left,
             top,
             bottom,
             front,
             back,
+            ibl: Ibl::empty(),
+            ibl_sun: Vector3::new(0.0, 1.0, 0.0),
+        };
+        skybox.update_lighting();
+        skybox
+    }
+
+    // recalcular la luz ambiental; llamar despues de cambiar el cielo
+    // el sol, la luna y las estrellas quedan fuera: su luz ya llega como luz directa
+    pub fn update_lighting(&mut self) {
+        let yaw = self.ibl_yaw();
+        self.ibl = Ibl::from_sky(|d| self.sky_color(&rotate_y(*d, yaw), false));
+        self.ibl_sun = self.sky.sun_direction();
+    }
+
+    // el cielo procedural sigue al sol real y no gira; los demas giran con yaw
+    fn follows_sun(&self) -> bool {
+        self.procedural && self.environment.is_none()
+    }
+
+    // giro del cielo que la luz ambiental deshace al consultarla
+    fn ibl_yaw(&self) -> f32 {
+        if self.follows_sun() { 0.0 } else { self.yaw }
+    }
+
+    // girar el cielo solo rota la consulta; se recalcula cuando el sol se movio lo suficiente
+    fn refresh_lighting(&mut self) {
+        if self.follows_sun() && self.sky.sun_direction().dot(self.ibl_sun) < IBL_STEP.cos() {
+            self.update_lighting();
+        }
+    }
+
+    // mover el sol del cielo procedural; la luz ambiental se actualiza cada tanto
+    pub fn set_sun_direction(&mut self, direction: Vector3) {
+        self.sky.set_sun_direction(direction);
+        self.refresh_lighting();
+    }
+
+    // girar el cielo segun el modo de orientacion, mas el giro por tiempo
+    pub fn update_orientation(&mut self, world_angle: f32, time: f32) {
+        let base = match self.orientation {
+            SkyOrientation::Fixed => 0.0,
+            SkyOrientation::World => world_angle,
+            SkyOrientation::SunPath => {
+                let sun = self.sky.sun_direction();
+                (-sun.z).atan2(sun.x)
+            }
+        };
+        self.yaw = base + self.spin * time;
+        self.refresh_lighting();
+    }
+
+    pub fn set_moon_phase(&mut self, phase: f32) {
+        self.sky.set_moon_phase(phase);
+    }
+
+    // para el titileo de las estrellas
+    pub fn set_time(&mut self, time: f32) {
+        self.sky.set_time(time);
+    }
+
+    pub fn moon_direction(&self) -> Vector3 {
+        self.sky.moon_direction()
+    }
+
+    // color e intensidad de la luz del sol segun el cielo que se ve:
+    // el procedural la tiñe al cruzar la atmosfera; el cubemap y el entorno no cambian
+    // de color con la hora, asi que el sol queda blanco y solo se apaga de noche
+    pub fn sun_light(&self) -> (Color, f32) {
+        if self.follows_sun() {
+            split_strength(self.sky.sun_color())
+        } else {
+            (Color::white(), self.sky.sun_visibility())
+        }
+    }
+
+    // color e intensidad relativa de la luz de luna (segun fase y altura)
+    pub fn moon_light(&self) -> (Color, f32) {
+        split_strength(self.sky.moon_color())
+    }
+
+    // luz difusa que llega del cielo a una superficie con esta normal (albedo 1)
+    pub fn irradiance(&self, normal: Vector3) -> Color {
+        self.ibl.irradiance(rotate_y(normal, -self.ibl_yaw()))
+    }
+
+    // reflejo borroso del cielo segun la rugosidad (0 = espejo)
+    pub fn prefiltered(&self, direction: Vector3, roughness: f32) -> Color {
+        self.ibl.prefiltered(rotate_y(direction, -self.ibl_yaw()), roughness, self.get_color(&direction))
+    }
+
+    // usar un mapa lat-long (.hdr, .exr o PNG) en lugar del cubemap
+    pub fn set_environment(&mut self, path: &str, rotation: f32, intensity: f32) {
+        match EnvironmentMap::load(path, rotation, intensity) {
+            Ok(env) => {
+                println!("Entorno cargado: {}", path);
+                self.environment = Some(env);
+                self.update_lighting();
+            }
+            Err(e) => eprintln!("No cargo el entorno {}: {}", path, e),
         }
     }
 
     pub fn get_color(&self, direction: &Vector3) -> Color {
+        self.sky_color(direction, true)
+    }
+
+    // cielo sin sol, luna ni estrellas: lo que ya llega como luz directa no se cuenta dos veces
+    pub fn ambient_color(&self, direction: &Vector3) -> Color {
+        self.sky_color(direction, false)
+    }
+
+    // with_sources: incluir sol, luna y estrellas
+    fn sky_color(&self, direction: &Vector3, with_sources: bool) -> Color {
+        // direccion en el marco del cielo girado
+        let local = rotate_y(*direction, -self.yaw);
+
+        if let Some(env) = &self.environment {
+            return env.get_color(&local);
+        }
+
+        if self.procedural {
+            // atmosfera, sol y luna siguen al sol real; solo las estrellas giran con el cielo
+            let color = self.sky.radiance(direction);
+            if !with_sources {
+                return color;
+            }
+            return color + self.sky.sun_disc(direction) + self.sky.moon_disc(direction) + self.sky.stars(&local);
+        }
+
+        let direction = &local;
+
         if let (Some(right), Some(left), Some(top), Some(bottom), Some(front), Some(back)) = (
             &self.right, &self.left, &self.top, &self.bottom, &self.front, &self.back
         ) {
@@ -107,4 +299,19 @@ impl Skybox {
             self.horizon_color * t + self.night_color * (1.0 - t)
         }
     }
-}
\ No newline at end of file
+}
+
+// misma convencion que rotate_vec3_y en main.rs
+fn rotate_y(v: Vector3, angle: f32) -> Vector3 {
+    let (s, c) = angle.sin_cos();
+    Vector3::new(c * v.x + s * v.z, v.y, -s * v.x + c * v.z)
+}
+
+// color normalizado (canal maximo 1) e intensidad
+fn split_strength(c: Color) -> (Color, f32) {
+    let strength = c.r.max(c.g).max(c.b);
+    if strength <= 0.0 {
+        return (Color::white(), 0.0);
+    }
+    (c * (1.0 / strength), strength)
+}
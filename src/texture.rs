@@ -1,89 +1,366 @@
 // texture.rs
-use image::{GenericImageView, Rgba};
+use image::{ColorType, GenericImageView, Rgba};
 use std::collections::HashMap;
-use crate::color::Color;
+use crate::color::{srgb_to_linear, Color};
+use crate::material::Material;
+
+// filtrado por material
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    Nearest,   // pixel art de cerca
+    Bilinear,
+    Trilinear, // bilinear entre dos niveles de mipmap
+}
+
+// que hacer con UVs fuera de [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+// como estan codificados los valores del archivo
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Srgb,   // colores (albedo, cielo): se decodifican a lineal al cargar
+    Linear, // datos (normales, rugosidad, mascaras): se usan tal cual
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sampler {
+    pub filter: TextureFilter,
+    pub wrap: WrapMode,
+}
+
+impl Sampler {
+    pub fn new(filter: TextureFilter, wrap: WrapMode) -> Self {
+        Sampler { filter, wrap }
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler::new(TextureFilter::Nearest, WrapMode::Repeat)
+    }
+}
+
+// pixeles RGBA en espacio lineal; el alfa siempre es lineal
+struct MipLevel {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
 
 pub struct Texture {
     pub width: u32,
     pub height: u32,
-    pixels: Vec<Rgba<u8>>,
+    // levels[0] es la imagen original, cada nivel es la mitad del anterior
+    levels: Vec<MipLevel>,
+}
+
+// sub-rectangulo en pixeles (un tile de un atlas)
+#[derive(Debug, Clone, Copy)]
+pub struct TileRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// atlas: una imagen con tiles nombrados, se referencian como "atlas:tile"
+// cada tile se guarda recortado con su propia cadena de mipmaps: los niveles chicos
+// promedian solo texeles del tile, aunque no este alineado a potencias de 2
+pub struct Atlas {
+    width: u32,
+    height: u32,
+    path: String,
+    tiles: HashMap<String, (TileRect, Texture)>,
 }
 
 impl Texture {
-    pub fn load(path: &str) -> Result<Self, image::ImageError> {
+    // PNG/JPG de 8 o 16 bits, o HDR de punto flotante (.hdr Radiance, .exr OpenEXR)
+    pub fn load(path: &str, space: ColorSpace) -> Result<Self, image::ImageError> {
+        // image decodifica .hdr a 8 bits si pasa por DynamicImage: leerlo directo
+        if path.to_ascii_lowercase().ends_with(".hdr") {
+            let file = std::io::BufReader::new(std::fs::File::open(path)?);
+            let decoder = image::codecs::hdr::HdrDecoder::new(file)?;
+            let (width, height) = (decoder.metadata().width, decoder.metadata().height);
+            let pixels = decoder.read_image_hdr()?.iter().map(|p| [p[0], p[1], p[2], 1.0]).collect();
+            return Ok(Texture::from_pixels(width, height, pixels));
+        }
+
         // adivinar formato desde el contenido
         let img = image::io::Reader::open(path)?
             .with_guessed_format()?
             .decode()?;
         let (width, height) = img.dimensions();
-        
-        let mut pixels = Vec::with_capacity((width * height) as usize);
-        for y in 0..height {
-            for x in 0..width {
-                pixels.push(img.get_pixel(x, y));
+
+        // los HDR ya guardan radiancia lineal sin limite en 1.0
+        let hdr = matches!(img.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+        let rgba = img.to_rgba32f();
+        let pixels = rgba
+            .pixels()
+            .map(|p| {
+                let [r, g, b, a] = p.0;
+                if hdr || space == ColorSpace::Linear {
+                    [r, g, b, a]
+                } else {
+                    [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+                }
+            })
+            .collect();
+
+        Ok(Texture::from_pixels(width, height, pixels))
+    }
+
+    // si junto al PNG hay una version HDR (mismo nombre, .exr o .hdr) se usa esa
+    pub fn hdr_variant(path: &str) -> Option<String> {
+        let base = std::path::Path::new(path);
+        ["exr", "hdr"]
+            .iter()
+            .map(|ext| base.with_extension(ext))
+            .find(|p| p.exists())
+            .map(|p| p.to_string_lossy().into_owned())
+    }
+
+    // decodificar pixeles de 8 bits (texturas de respaldo); los mipmaps se promedian ya en lineal
+    fn from_rgba8(width: u32, height: u32, pixels: &[Rgba<u8>], space: ColorSpace) -> Self {
+        let mut table = [0.0f32; 256];
+        for (i, value) in table.iter_mut().enumerate() {
+            let c = i as f32 / 255.0;
+            *value = match space {
+                ColorSpace::Srgb => srgb_to_linear(c),
+                ColorSpace::Linear => c,
+            };
+        }
+        let pixels = pixels
+            .iter()
+            .map(|p| [table[p[0] as usize], table[p[1] as usize], table[p[2] as usize], p[3] as f32 / 255.0])
+            .collect();
+        Texture::from_pixels(width, height, pixels)
+    }
+
+    // copia de un rectangulo del nivel 0 con su propia cadena de mipmaps
+    fn crop(&self, rect: TileRect) -> Texture {
+        let lvl = &self.levels[0];
+        let pixels = (rect.y..rect.y + rect.height)
+            .flat_map(|y| (rect.x..rect.x + rect.width).map(move |x| lvl.pixels[(y * lvl.width + x) as usize]))
+            .collect();
+        Texture::from_pixels(rect.width, rect.height, pixels)
+    }
+
+    // construye la cadena de mipmaps (promedio 2x2) hasta llegar a 1x1
+    fn from_pixels(width: u32, height: u32, pixels: Vec<[f32; 4]>) -> Self {
+        let mut levels = vec![MipLevel { width, height, pixels }];
+        loop {
+            let prev = levels.last().unwrap();
+            if prev.width == 1 && prev.height == 1 { break; }
+            let (w, h) = ((prev.width / 2).max(1), (prev.height / 2).max(1));
+            let mut pixels = Vec::with_capacity((w * h) as usize);
+            for y in 0..h {
+                for x in 0..w {
+                    let mut sum = [0.0f32; 4];
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let sx = (x * 2 + dx).min(prev.width - 1);
+                        let sy = (y * 2 + dy).min(prev.height - 1);
+                        let p = prev.pixels[(sy * prev.width + sx) as usize];
+                        for c in 0..4 { sum[c] += p[c]; }
+                    }
+                    pixels.push(sum.map(|c| c / 4.0));
+                }
             }
+            levels.push(MipLevel { width: w, height: h, pixels });
         }
-        
-        Ok(Texture { width, height, pixels })
+        Texture { width, height, levels }
     }
 
     pub fn get_pixel(&self, u: f32, v: f32) -> Color {
-        // envolver UVs
-        let u = u.rem_euclid(1.0);
-        let v = v.rem_euclid(1.0);
-        
-        let x = ((u * self.width as f32) as u32).min(self.width - 1);
-        let y = ((v * self.height as f32) as u32).min(self.height - 1);
-        
-        let idx = (y * self.width + x) as usize;
-        let pixel = self.pixels[idx];
+        self.sample(u, v, self.full_rect(), Sampler::default(), 0.0)
+    }
+
+    // bilinear sobre toda la imagen, UV repetida (mapas de entorno)
+    pub fn get_pixel_bilinear(&self, u: f32, v: f32) -> Color {
+        self.sample(u, v, self.full_rect(), Sampler::new(TextureFilter::Bilinear, WrapMode::Repeat), 0.0)
+    }
+
+    fn full_rect(&self) -> TileRect {
+        TileRect { x: 0, y: 0, width: self.width, height: self.height }
+    }
+
+    // muestrear dentro de un rectangulo: la UV envuelve dentro de el
+    // los mipmaps solo respetan el rectangulo si esta alineado a potencias de 2,
+    // por eso los tiles de un atlas se muestrean sobre su propio recorte
+    // footprint: tamaño del pixel de pantalla en unidades UV (para elegir el mipmap)
+    pub fn sample(&self, u: f32, v: f32, rect: TileRect, sampler: Sampler, footprint: f32) -> Color {
+        match sampler.filter {
+            TextureFilter::Nearest => self.sample_nearest(u, v, rect, sampler.wrap),
+            TextureFilter::Bilinear => self.sample_bilinear(u, v, rect, sampler.wrap, 0),
+            TextureFilter::Trilinear => {
+                let max_level = self.max_level(rect);
+                let size = rect.width.max(rect.height) as f32;
+                let lod = (footprint * size).max(1e-6).log2();
+                // magnificacion: texel nitido, los mipmaps solo sirven para achicar
+                if lod <= 0.0 {
+                    return self.sample_nearest(u, v, rect, sampler.wrap);
+                }
+                let lod = lod.min(max_level as f32);
+                let l0 = lod.floor() as usize;
+                let l1 = (l0 + 1).min(max_level);
+                let f = lod - l0 as f32;
+                let a = self.sample_bilinear(u, v, rect, sampler.wrap, l0);
+                if l1 == l0 || f <= 0.0 {
+                    return a;
+                }
+                let b = self.sample_bilinear(u, v, rect, sampler.wrap, l1);
+                a * (1.0 - f) + b * f
+            }
+        }
+    }
+
+    // ultimo nivel donde el tile sigue teniendo al menos 1 pixel
+    fn max_level(&self, rect: TileRect) -> usize {
+        let smallest = rect.width.min(rect.height).max(1);
+        ((31 - smallest.leading_zeros()) as usize).min(self.levels.len() - 1)
+    }
+
+    fn sample_nearest(&self, u: f32, v: f32, rect: TileRect, wrap: WrapMode) -> Color {
+        let (x, y) = nearest_texel(u, v, rect, wrap);
+        self.texel(0, x, y)
+    }
+
+    // alfa del texel mas cercano en el nivel 0 (1 = opaco)
+    // sin filtrar: los recortes quedan nitidos como en Minecraft y no cambian con la distancia
+    pub fn sample_alpha(&self, u: f32, v: f32, rect: TileRect, wrap: WrapMode) -> f32 {
+        let (x, y) = nearest_texel(u, v, rect, wrap);
+        let lvl = &self.levels[0];
+        let pixel = lvl.pixels[(y.min(lvl.height - 1) * lvl.width + x.min(lvl.width - 1)) as usize];
+        pixel[3]
+    }
+
+    fn sample_bilinear(&self, u: f32, v: f32, rect: TileRect, wrap: WrapMode, level: usize) -> Color {
+        // el tile escalado al nivel de mipmap
+        let (rx, ry) = (rect.x >> level, rect.y >> level);
+        let (rw, rh) = ((rect.width >> level).max(1), (rect.height >> level).max(1));
+
+        // centros de texel en x + 0.5
+        let x = u * rw as f32 - 0.5;
+        let y = v * rh as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let xa = rx + wrap_index(x0, rw, wrap);
+        let xb = rx + wrap_index(x0 + 1, rw, wrap);
+        let ya = ry + wrap_index(y0, rh, wrap);
+        let yb = ry + wrap_index(y0 + 1, rh, wrap);
+
+        let top = self.texel(level, xa, ya) * (1.0 - fx) + self.texel(level, xb, ya) * fx;
+        let bottom = self.texel(level, xa, yb) * (1.0 - fx) + self.texel(level, xb, yb) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    fn texel(&self, level: usize, x: u32, y: u32) -> Color {
+        let lvl = &self.levels[level];
+        let x = x.min(lvl.width - 1);
+        let y = y.min(lvl.height - 1);
+        let pixel = lvl.pixels[(y * lvl.width + x) as usize];
         
-        Color::new(
-            pixel[0] as f32 / 255.0,
-            pixel[1] as f32 / 255.0,
-            pixel[2] as f32 / 255.0,
-        )
+        Color::new(pixel[0], pixel[1], pixel[2])
     }
 
-    // texturas awa y fuego
-    pub fn get_pixel_animated(&self, u: f32, v: f32, time: f32) -> Color {
+    pub fn animate_uv(u: f32, v: f32, time: f32) -> (f32, f32) {
         // animacian de ondas
         let u_offset = (time * 0.5).sin() * 0.1;
         let v_offset = (time * 0.3).cos() * 0.1;
         
-        self.get_pixel(u + u_offset, v + v_offset)
+        (u + u_offset, v + v_offset)
     }
 }
 
+// texel (x, y) del nivel 0 que contiene la UV, dentro del tile
+fn nearest_texel(u: f32, v: f32, rect: TileRect, wrap: WrapMode) -> (u32, u32) {
+    let x = wrap_index((u * rect.width as f32).floor() as i64, rect.width, wrap);
+    let y = wrap_index((v * rect.height as f32).floor() as i64, rect.height, wrap);
+    (rect.x + x, rect.y + y)
+}
+
+// indice de texel dentro de [0, n) segun el modo de wrap
+fn wrap_index(i: i64, n: u32, wrap: WrapMode) -> u32 {
+    let n = n.max(1) as i64;
+    let i = match wrap {
+        WrapMode::Repeat => i.rem_euclid(n),
+        WrapMode::Clamp => i.clamp(0, n - 1),
+        WrapMode::Mirror => {
+            let m = i.rem_euclid(2 * n);
+            if m >= n { 2 * n - 1 - m } else { m }
+        }
+    };
+    i as u32
+}
+
 pub struct TextureManager {
     textures: HashMap<String, Texture>,
+    paths: HashMap<String, String>, // archivo de origen (para exportar)
+    atlases: HashMap<String, Atlas>,
 }
 
 impl TextureManager {
     pub fn new() -> Self {
         let mut manager = TextureManager {
             textures: HashMap::new(),
+            paths: HashMap::new(),
+            atlases: HashMap::new(),
         };
         
-        // cargar texturas
-        manager.load_texture("grass", "assets/textures/grass.png");
-        manager.load_texture("wood", "assets/textures/wood.png");
-        manager.load_texture("water", "assets/textures/water.png");
-        manager.load_texture("glass", "assets/textures/glass.png");
-        manager.load_texture("stone", "assets/textures/stone.png");
-        manager.load_texture("steve", "assets/textures/steve.png");
-        manager.load_texture("brick", "assets/textures/brick.png");
-        manager.load_texture("woodhouse", "assets/textures/woodhouse.png");
-        manager.load_texture("fire", "assets/textures/fire.png");
+        // cargar texturas (las de color vienen en sRGB; mapas de datos irian con ColorSpace::Linear)
+        manager.load_texture("grass", "assets/textures/grass.png", ColorSpace::Srgb);
+        manager.load_texture("wood", "assets/textures/wood.png", ColorSpace::Srgb);
+        manager.load_texture("water", "assets/textures/water.png", ColorSpace::Srgb);
+        manager.load_texture("glass", "assets/textures/glass.png", ColorSpace::Srgb);
+        manager.load_texture("stone", "assets/textures/stone.png", ColorSpace::Srgb);
+        manager.load_texture("steve", "assets/textures/steve.png", ColorSpace::Srgb);
+        manager.load_texture("brick", "assets/textures/brick.png", ColorSpace::Srgb);
+        manager.load_texture("woodhouse", "assets/textures/woodhouse.png", ColorSpace::Srgb);
+        manager.load_texture("fire", "assets/textures/fire.png", ColorSpace::Srgb);
+
+        // texturas por cara: si no existen se usa la textura base del material
+        manager.load_optional_texture("grass_side", "assets/textures/grass_side.png", ColorSpace::Srgb);
+        manager.load_optional_texture("dirt", "assets/textures/dirt.png", ColorSpace::Srgb);
+        manager.load_optional_texture("wood_top", "assets/textures/wood_top.png", ColorSpace::Srgb);
+        manager.load_optional_texture("leaves", "assets/textures/leaves.png", ColorSpace::Srgb);
+        // los bloques de tierra (Material::dirt) la usan como textura base: sin dirt.png
+        // va el marron de respaldo, sin avisar como las opcionales
+        if !manager.has_texture("dirt") {
+            manager.textures.insert("dirt".to_string(), Self::create_fallback_texture("dirt"));
+        }
+
+        // atlas: cada assets/textures/<nombre>.atlas define tiles de una imagen
+        if let Ok(entries) = std::fs::read_dir("assets/textures") {
+            let mut atlas_files: Vec<_> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "atlas"))
+                .collect();
+            atlas_files.sort();
+            for path in atlas_files {
+                manager.load_atlas(&path.to_string_lossy());
+            }
+        }
         
         manager
     }
 
-    fn load_texture(&mut self, name: &str, path: &str) {
-        match Texture::load(path) {
+    fn load_texture(&mut self, name: &str, path: &str, space: ColorSpace) {
+        let hdr_path = Texture::hdr_variant(path);
+        match Texture::load(hdr_path.as_deref().unwrap_or(path), space) {
             Ok(texture) => {
-                println!("cargaron texturas: {}", name);
+                println!("cargaron texturas: {}{}", name, if hdr_path.is_some() { " (HDR)" } else { "" });
                 self.textures.insert(name.to_string(), texture);
+                // para exportar se sigue usando el PNG (glTF no acepta HDR)
+                if std::path::Path::new(path).exists() {
+                    self.paths.insert(name.to_string(), path.to_string());
+                }
             }
             Err(e) => {
                 eprintln!("No cargaron texturas {}: {}", path, e);
@@ -93,10 +370,109 @@ impl TextureManager {
         }
     }
 
+    // como load_texture pero sin textura de respaldo si falta el archivo
+    fn load_optional_texture(&mut self, name: &str, path: &str, space: ColorSpace) {
+        if std::path::Path::new(path).exists() || Texture::hdr_variant(path).is_some() {
+            self.load_texture(name, path, space);
+        }
+    }
+
+    // formato del archivo .atlas (rutas relativas al archivo):
+    //   image = terrain.png
+    //   tile_size = 16          tamaño de celda para tiles por indice
+    //   color_space = linear    opcional, por defecto srgb
+    //   grass_top = 0 0         columna fila
+    //   torch = 64 32 16 16     x y ancho alto en pixeles
+    fn load_atlas(&mut self, path: &str) {
+        let name = std::path::Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let content = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("No cargo el atlas {}: {}", path, e);
+                return;
+            }
+        };
+        let dir = std::path::Path::new(path).parent().unwrap_or(std::path::Path::new(""));
+
+        let mut image_path: Option<String> = None;
+        let mut tile_size = (16u32, 16u32);
+        let mut space = ColorSpace::Srgb;
+        let mut tiles: HashMap<String, TileRect> = HashMap::new();
+        for (line_no, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("{}:{}: falta '='", path, line_no + 1);
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let nums: Vec<u32> = value.split_whitespace().filter_map(|n| n.parse().ok()).collect();
+            match (key, nums.len()) {
+                ("image", _) => image_path = Some(dir.join(value).to_string_lossy().into_owned()),
+                ("color_space", _) => match value {
+                    "srgb" => space = ColorSpace::Srgb,
+                    "linear" => space = ColorSpace::Linear,
+                    _ => eprintln!("{}:{}: color_space debe ser srgb o linear", path, line_no + 1),
+                },
+                ("tile_size", 1) => tile_size = (nums[0], nums[0]),
+                ("tile_size", 2) => tile_size = (nums[0], nums[1]),
+                (_, 2) => {
+                    tiles.insert(key.to_string(), TileRect {
+                        x: nums[0] * tile_size.0,
+                        y: nums[1] * tile_size.1,
+                        width: tile_size.0,
+                        height: tile_size.1,
+                    });
+                }
+                (_, 4) => {
+                    tiles.insert(key.to_string(), TileRect { x: nums[0], y: nums[1], width: nums[2], height: nums[3] });
+                }
+                _ => eprintln!("{}:{}: linea de atlas invalida", path, line_no + 1),
+            }
+        }
+
+        let Some(image_path) = image_path else {
+            eprintln!("El atlas {} no tiene 'image'", path);
+            return;
+        };
+        let texture = match Texture::load(&image_path, space) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("No cargo la imagen del atlas {}: {}", image_path, e);
+                return;
+            }
+        };
+
+        // descartar tiles fuera de la imagen
+        tiles.retain(|tile, r| {
+            let ok = r.width > 0 && r.height > 0
+                && r.x + r.width <= texture.width && r.y + r.height <= texture.height;
+            if !ok {
+                eprintln!("Tile {}:{} fuera de la imagen", name, tile);
+            }
+            ok
+        });
+        println!("cargo atlas: {} ({} tiles)", name, tiles.len());
+        let tiles = tiles.into_iter().map(|(tile, r)| (tile, (r, texture.crop(r)))).collect();
+        self.atlases.insert(name, Atlas { width: texture.width, height: texture.height, path: image_path, tiles });
+    }
+
+    // resolver "atlas:tile": el atlas, el rectangulo del tile y su textura recortada
+    fn atlas_tile(&self, name: &str) -> Option<(&Atlas, TileRect, &Texture)> {
+        let (atlas_name, tile) = name.split_once(':')?;
+        let atlas = self.atlases.get(atlas_name)?;
+        let (rect, texture) = atlas.tiles.get(tile)?;
+        Some((atlas, *rect, texture))
+    }
+
     fn create_fallback_texture(name: &str) -> Texture {
         // textura de respaldo 2x2
         let color = match name {
             "grass" => Rgba([50, 200, 50, 255]),
+            "dirt" => Rgba([120, 85, 55, 255]),
             "wood" => Rgba([150, 100, 50, 255]),
             "water" => Rgba([50, 100, 200, 255]),
             "glass" => Rgba([200, 220, 255, 255]),
@@ -107,27 +483,90 @@ impl TextureManager {
             _ => Rgba([255, 0, 255, 255]), 
         };
         
-        Texture {
-            width: 2,
-            height: 2,
-            pixels: vec![color; 4],
-        }
+        Texture::from_rgba8(2, 2, &[color; 4], ColorSpace::Srgb)
     }
 
     pub fn get_texture(&self, name: &str) -> Option<&Texture> {
         self.textures.get(name)
     }
 
-    pub fn get_color(&self, name: &str, u: f32, v: f32, time: f32, animated: bool) -> Color {
-        if let Some(texture) = self.get_texture(name) {
-            if animated {
-                texture.get_pixel_animated(u, v, time)
-            } else {
-                texture.get_pixel(u, v)
-            }
+    // textura suelta o tile de atlas
+    pub fn has_texture(&self, name: &str) -> bool {
+        self.textures.contains_key(name) || self.atlas_tile(name).is_some()
+    }
+
+    // ruta del PNG original, None si se uso la textura de respaldo
+    pub fn texture_path(&self, name: &str) -> Option<&str> {
+        if let Some((atlas, _, _)) = self.atlas_tile(name) {
+            return Some(atlas.path.as_str());
+        }
+        self.paths.get(name).map(|p| p.as_str())
+    }
+
+    // rectangulo UV normalizado [u0, v0, u1, v1] de un tile dentro de su atlas
+    pub fn uv_rect(&self, name: &str) -> Option<[f32; 4]> {
+        let (atlas, r, _) = self.atlas_tile(name)?;
+        let (w, h) = (atlas.width as f32, atlas.height as f32);
+        Some([
+            r.x as f32 / w,
+            r.y as f32 / h,
+            (r.x + r.width) as f32 / w,
+            (r.y + r.height) as f32 / h,
+        ])
+    }
+
+    // color de la textura con el filtrado y wrap del material
+    pub fn sample(&self, name: &str, u: f32, v: f32, footprint: f32, time: f32, material: &Material) -> Color {
+        let (u, v) = if material.animated { Texture::animate_uv(u, v, time) } else { (u, v) };
+        if let Some((_, _, tile)) = self.atlas_tile(name) {
+            tile.sample(u, v, tile.full_rect(), material.sampler, footprint)
+        } else if let Some(texture) = self.get_texture(name) {
+            texture.sample(u, v, texture.full_rect(), material.sampler, footprint)
         } else {
             // respaldo
             Color::new(1.0, 0.0, 1.0)
         }
     }
-}
\ No newline at end of file
+
+    // alfa de la textura en la UV (1.0 si no hay textura)
+    pub fn alpha(&self, name: &str, u: f32, v: f32, time: f32, material: &Material) -> f32 {
+        let (u, v) = if material.animated { Texture::animate_uv(u, v, time) } else { (u, v) };
+        if let Some((_, _, tile)) = self.atlas_tile(name) {
+            tile.sample_alpha(u, v, tile.full_rect(), material.sampler.wrap)
+        } else if let Some(texture) = self.get_texture(name) {
+            texture.sample_alpha(u, v, texture.full_rect(), material.sampler.wrap)
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn atlas_tile_mipmaps_do_not_bleed_into_neighbours() {
+        // dos tiles de 3x3 uno al lado del otro: rojo y verde
+        let pixels = (0..3)
+            .flat_map(|_| (0..6).map(|x| if x < 3 { [1.0, 0.0, 0.0, 1.0] } else { [0.0, 1.0, 0.0, 1.0] }))
+            .collect();
+        let atlas = Texture::from_pixels(6, 3, pixels);
+        let red = atlas.crop(TileRect { x: 0, y: 0, width: 3, height: 3 });
+        let sampler = Sampler::new(TextureFilter::Trilinear, WrapMode::Repeat);
+        for footprint in [0.0, 0.5, 1.0, 4.0] {
+            let c = red.sample(0.9, 0.5, red.full_rect(), sampler, footprint);
+            assert!((c.r - 1.0).abs() < 1e-5 && c.g.abs() < 1e-5, "footprint {}: {:?}", footprint, c);
+        }
+    }
+
+    #[test]
+    fn trilinear_magnification_keeps_texels_sharp() {
+        // tablero 2x2: de cerca cada texel debe verse entero, sin mezclar con el vecino
+        let pixels = vec![[1.0, 1.0, 1.0, 1.0], [0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]];
+        let tex = Texture::from_pixels(2, 2, pixels);
+        let sampler = Sampler::new(TextureFilter::Trilinear, WrapMode::Repeat);
+        let c = tex.sample(0.45, 0.25, tex.full_rect(), sampler, 0.01);
+        assert!((c.r - 1.0).abs() < 1e-5, "{:?}", c);
+    }
+}
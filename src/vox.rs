@@ -0,0 +1,519 @@
+// vox.rs
+// importador de modelos MagicaVoxel (.vox)
+use raylib::prelude::*;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::material::{Material, MaterialType};
+
+type Rotation = [[i32; 3]; 3];
+// (modelo, rotacion, traslacion) en coordenadas de MagicaVoxel
+type Instance = (usize, Rotation, (i32, i32, i32));
+
+struct VoxModel {
+    size: (i32, i32, i32),
+    voxels: Vec<(u8, u8, u8, u8)>, // x, y, z, indice de color
+}
+
+// nodos del grafo de escena (nTRN / nGRP / nSHP)
+enum VoxNode {
+    Transform { child: i32, rotation: Rotation, translation: (i32, i32, i32) },
+    Group { children: Vec<i32> },
+    Shape { models: Vec<i32> },
+}
+
+// lector de bytes little-endian
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.pos + n > self.data.len() {
+            return Err(invalid("archivo .vox truncado"));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        let b = self.bytes(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.i32()?.max(0) as usize;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>> {
+        let count = self.i32()?.max(0);
+        let mut dict = HashMap::new();
+        for _ in 0..count {
+            let key = self.string()?;
+            let value = self.string()?;
+            dict.insert(key, value);
+        }
+        Ok(dict)
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+// cargar un .vox y convertir cada voxel en un cubo
+// position: donde queda el centro de la base del modelo, scale: tamaño de cada voxel
+pub fn load_vox(path: &str, position: Vector3, scale: f32) -> Result<Vec<Cube>> {
+    let data = std::fs::read(path)?;
+    parse_vox(&data, position, scale)
+}
+
+fn parse_vox(data: &[u8], position: Vector3, scale: f32) -> Result<Vec<Cube>> {
+    let mut r = Reader::new(data);
+    if r.bytes(4)? != b"VOX " {
+        return Err(invalid("no es un archivo .vox"));
+    }
+    let _version = r.i32()?;
+
+    // MAIN envuelve todos los demas chunks
+    if r.bytes(4)? != b"MAIN" {
+        return Err(invalid("falta el chunk MAIN"));
+    }
+    let main_content = r.i32()?.max(0) as usize;
+    let main_children = r.i32()?.max(0) as usize;
+    r.bytes(main_content)?;
+    let end = (r.pos + main_children).min(data.len());
+
+    let mut models: Vec<VoxModel> = Vec::new();
+    let mut pending_size: Option<(i32, i32, i32)> = None;
+    let mut palette: Option<Vec<[u8; 4]>> = None;
+    let mut materials: HashMap<usize, HashMap<String, String>> = HashMap::new();
+    let mut nodes: HashMap<i32, VoxNode> = HashMap::new();
+
+    while r.pos + 12 <= end {
+        let id = r.bytes(4)?;
+        let content_size = r.i32()?.max(0) as usize;
+        let children_size = r.i32()?.max(0) as usize;
+        let mut c = Reader::new(r.bytes(content_size)?);
+        r.bytes(children_size)?;
+
+        match id {
+            b"SIZE" => {
+                pending_size = Some((c.i32()?, c.i32()?, c.i32()?));
+            }
+            b"XYZI" => {
+                let count = c.i32()?.max(0);
+                let mut voxels = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    voxels.push((c.u8()?, c.u8()?, c.u8()?, c.u8()?));
+                }
+                let size = pending_size.take().ok_or_else(|| invalid("XYZI sin SIZE"))?;
+                models.push(VoxModel { size, voxels });
+            }
+            b"RGBA" => {
+                let mut colors = Vec::with_capacity(256);
+                for _ in 0..256 {
+                    let b = c.bytes(4)?;
+                    colors.push([b[0], b[1], b[2], b[3]]);
+                }
+                palette = Some(colors);
+            }
+            b"MATL" => {
+                let mat_id = c.i32()?.max(0) as usize;
+                materials.insert(mat_id, c.dict()?);
+            }
+            b"nTRN" => {
+                let node_id = c.i32()?;
+                let _attrs = c.dict()?;
+                let child = c.i32()?;
+                let _reserved = c.i32()?;
+                let _layer = c.i32()?;
+                let frames = c.i32()?;
+                let mut rotation = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+                let mut translation = (0, 0, 0);
+                // solo se usa el primer frame (sin animacion)
+                if frames > 0 {
+                    let frame = c.dict()?;
+                    if let Some(rv) = frame.get("_r").and_then(|s| s.parse::<u8>().ok()) {
+                        rotation = decode_rotation(rv);
+                    }
+                    if let Some(t) = frame.get("_t") {
+                        let p: Vec<i32> = t.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+                        if p.len() == 3 {
+                            translation = (p[0], p[1], p[2]);
+                        }
+                    }
+                }
+                nodes.insert(node_id, VoxNode::Transform { child, rotation, translation });
+            }
+            b"nGRP" => {
+                let node_id = c.i32()?;
+                let _attrs = c.dict()?;
+                let count = c.i32()?.max(0);
+                let mut children = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    children.push(c.i32()?);
+                }
+                nodes.insert(node_id, VoxNode::Group { children });
+            }
+            b"nSHP" => {
+                let node_id = c.i32()?;
+                let _attrs = c.dict()?;
+                let count = c.i32()?.max(0);
+                let mut shape_models = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    shape_models.push(c.i32()?);
+                    let _model_attrs = c.dict()?;
+                }
+                nodes.insert(node_id, VoxNode::Shape { models: shape_models });
+            }
+            // PACK, LAYR, rOBJ, rCAM, NOTE, IMAP no afectan la geometria
+            _ => {}
+        }
+    }
+
+    // un material por indice de paleta (1..255)
+    let palette = palette.unwrap_or_else(default_palette);
+    let mut palette_materials: Vec<Option<Material>> = vec![None; 256];
+    let material_for = |index: u8, cache: &mut Vec<Option<Material>>| -> Material {
+        let i = index as usize;
+        if cache[i].is_none() {
+            let rgba = palette[(i + 255) % 256];
+            cache[i] = Some(vox_material(rgba, materials.get(&i)));
+        }
+        cache[i].clone().unwrap()
+    };
+
+    let mut instances: Vec<Instance> = Vec::new();
+    if nodes.contains_key(&0) {
+        collect_instances(&nodes, 0, IDENTITY, (0, 0, 0), &mut instances, 0);
+    } else {
+        // archivo sin grafo de escena: todos los modelos en el origen
+        for i in 0..models.len() {
+            instances.push((i, IDENTITY, (0, 0, 0)));
+        }
+    }
+
+    let mut cubes = Vec::new();
+    let mut min_y = f32::INFINITY;
+    for (model_index, rot, t) in instances {
+        let Some(model) = models.get(model_index) else { continue };
+        // MagicaVoxel rota alrededor del centro entero del modelo
+        let pivot = (model.size.0 / 2, model.size.1 / 2, model.size.2 / 2);
+        for &(x, y, z, ci) in &model.voxels {
+            if ci == 0 { continue; }
+            let local = [
+                x as f32 - pivot.0 as f32 + 0.5,
+                y as f32 - pivot.1 as f32 + 0.5,
+                z as f32 - pivot.2 as f32 + 0.5,
+            ];
+            let mut p = [0.0f32; 3];
+            for (row, out) in rot.iter().zip(p.iter_mut()) {
+                *out = row[0] as f32 * local[0] + row[1] as f32 * local[1] + row[2] as f32 * local[2];
+            }
+            // Z arriba en MagicaVoxel -> Y arriba en el raytracer
+            let world = Vector3::new(p[0] + t.0 as f32, p[2] + t.2 as f32, -(p[1] + t.1 as f32));
+            min_y = min_y.min(world.y - 0.5);
+            cubes.push(Cube::new(world, scale, material_for(ci, &mut palette_materials)));
+        }
+    }
+
+    // apoyar la base del modelo en position
+    for cube in &mut cubes {
+        let local = cube.center - Vector3::new(0.0, min_y, 0.0);
+        cube.center = position + local * scale;
+    }
+
+    Ok(cubes)
+}
+
+// paleta de MagicaVoxel para archivos sin RGBA, en el orden del chunk RGBA (la entrada
+// k es el color k + 1): cubo 6x6x6 sin el negro, rampas de rojo, verde, azul y gris
+fn default_palette() -> Vec<[u8; 4]> {
+    const STEPS: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    let mut palette = Vec::with_capacity(256);
+    for r in STEPS {
+        for g in STEPS {
+            for b in STEPS {
+                if r | g | b != 0 {
+                    palette.push([r, g, b, 255]);
+                }
+            }
+        }
+    }
+    for v in RAMP { palette.push([v, 0, 0, 255]); }
+    for v in RAMP { palette.push([0, v, 0, 255]); }
+    for v in RAMP { palette.push([0, 0, v, 255]); }
+    for v in RAMP { palette.push([v, v, v, 255]); }
+    // el color 0 es el vacio
+    palette.push([0, 0, 0, 0]);
+    palette
+}
+
+const IDENTITY: Rotation = [[1, 0, 0], [0, 1, 0], [0, 0, 1]];
+
+fn collect_instances(
+    nodes: &HashMap<i32, VoxNode>,
+    id: i32,
+    rot: Rotation,
+    t: (i32, i32, i32),
+    out: &mut Vec<Instance>,
+    depth: u32,
+) {
+    // evitar ciclos en archivos corruptos
+    if depth > 64 { return; }
+    match nodes.get(&id) {
+        Some(VoxNode::Transform { child, rotation, translation }) => {
+            // componer: padre * hijo
+            let new_rot = mat_mul(rot, *rotation);
+            let tv = [translation.0, translation.1, translation.2];
+            let rt: Vec<i32> = rot.iter().map(|row| row[0] * tv[0] + row[1] * tv[1] + row[2] * tv[2]).collect();
+            let new_t = (t.0 + rt[0], t.1 + rt[1], t.2 + rt[2]);
+            collect_instances(nodes, *child, new_rot, new_t, out, depth + 1);
+        }
+        Some(VoxNode::Group { children }) => {
+            for &c in children {
+                collect_instances(nodes, c, rot, t, out, depth + 1);
+            }
+        }
+        Some(VoxNode::Shape { models }) => {
+            for &m in models {
+                out.push((m.max(0) as usize, rot, t));
+            }
+        }
+        None => {}
+    }
+}
+
+fn mat_mul(a: Rotation, b: Rotation) -> Rotation {
+    let mut m = [[0; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            m[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+    m
+}
+
+// rotacion empaquetada en un byte (ver especificacion de MagicaVoxel)
+fn decode_rotation(r: u8) -> Rotation {
+    let i0 = (r & 3) as usize;
+    let i1 = ((r >> 2) & 3) as usize;
+    if i0 > 2 || i1 > 2 || i0 == i1 {
+        return IDENTITY;
+    }
+    let i2 = 3 - i0 - i1;
+    let sign = |bit: u8| if r & (1 << bit) != 0 { -1 } else { 1 };
+    let mut m = [[0; 3]; 3];
+    m[0][i0] = sign(4);
+    m[1][i1] = sign(5);
+    m[2][i2] = sign(6);
+    m
+}
+
+// traducir un MATL de MagicaVoxel a nuestro Material
+fn vox_material(rgba: [u8; 4], props: Option<&HashMap<String, String>>) -> Material {
+    // la paleta esta en sRGB
+    let albedo = Color::from_srgb(rgba[0] as f32 / 255.0, rgba[1] as f32 / 255.0, rgba[2] as f32 / 255.0);
+    let get = |key: &str, default: f32| -> f32 {
+        props.and_then(|p| p.get(key)).and_then(|v| v.parse().ok()).unwrap_or(default)
+    };
+    let kind = props.and_then(|p| p.get("_type")).map(|s| s.as_str()).unwrap_or("_diffuse");
+
+    match kind {
+        "_metal" => {
+            let metal = get("_metal", 0.0).clamp(0.0, 1.0);
+            let rough = get("_rough", 0.1).clamp(0.0, 1.0);
+            Material::new(
+                MaterialType::Reflective,
+                albedo,
+                0.5 + 0.5 * (1.0 - rough),
+                metal * (1.0 - rough * 0.5),
+                0.0,
+                1.0,
+                None,
+                None,
+                false,
+            )
+        }
+        "_glass" | "_blend" => {
+            // _ri es el indice real, _ior se guarda como ior - 1
+            let ior = props
+                .and_then(|p| p.get("_ri"))
+                .and_then(|v| v.parse::<f32>().ok())
+                .unwrap_or(1.0 + get("_ior", 0.5));
+            let trans = get("_trans", get("_alpha", 0.8)).clamp(0.0, 1.0);
+            Material::new(
+                MaterialType::Refractive,
+                albedo,
+                0.9,
+                0.1,
+                trans,
+                ior,
+                None,
+                None,
+                false,
+            )
+        }
+        "_emit" => {
+            // _flux es un exponente de potencia
+            let emit = get("_emit", 1.0) * 2f32.powf(get("_flux", 0.0));
+            Material::new(
+                MaterialType::Emissive,
+                albedo,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+                Some(albedo * emit),
+                None,
+                false,
+            )
+        }
+        _ => Material::new(
+            MaterialType::Diffuse,
+            albedo,
+            get("_sp", 0.1).clamp(0.0, 1.0),
+            0.0,
+            0.0,
+            1.0,
+            None,
+            None,
+            false,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{assert_cube, le_i32s, TempFile};
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((content.len() as i32).to_le_bytes());
+        out.extend((children.len() as i32).to_le_bytes());
+        out.extend(content);
+        out.extend(children);
+        out
+    }
+
+    fn vox_file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = b"VOX ".to_vec();
+        out.extend(150i32.to_le_bytes());
+        out.extend(chunk(b"MAIN", &[], &chunks.concat()));
+        out
+    }
+
+    fn model(size: (i32, i32, i32), voxels: &[[u8; 4]]) -> Vec<Vec<u8>> {
+        let size = le_i32s(&[size.0, size.1, size.2]);
+        let mut xyzi = le_i32s(&[voxels.len() as i32]);
+        xyzi.extend(voxels.concat());
+        vec![chunk(b"SIZE", &size, &[]), chunk(b"XYZI", &xyzi, &[])]
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn voxels_sit_on_the_base_with_palette_colors() {
+        let mut palette = vec![[0u8, 0, 0, 255]; 256];
+        palette[0] = [255, 0, 0, 255];
+        palette[1] = [0, 255, 0, 255];
+        let mut chunks = model((1, 1, 2), &[[0, 0, 0, 1], [0, 0, 1, 2]]);
+        chunks.push(chunk(b"RGBA", &palette.concat(), &[]));
+
+        let cubes = parse_vox(&vox_file(&chunks), Vector3::new(10.0, 0.0, 0.0), 0.5).unwrap();
+        assert_eq!(cubes.len(), 2);
+        // Z de MagicaVoxel es Y: el voxel de abajo apoya en position
+        assert_cube(&cubes[0], [10.25, 0.25, -0.25], 0.5);
+        assert_cube(&cubes[1], [10.25, 0.75, -0.25], 0.5);
+        assert!(close(cubes[0].material.albedo.r, 1.0) && close(cubes[0].material.albedo.g, 0.0));
+        assert!(close(cubes[1].material.albedo.g, 1.0) && close(cubes[1].material.albedo.r, 0.0));
+    }
+
+    #[test]
+    fn missing_rgba_uses_the_default_palette() {
+        let palette = default_palette();
+        assert_eq!(palette.len(), 256);
+        assert_eq!(palette[0], [255, 255, 255, 255]);
+        assert_eq!(palette[6], [255, 204, 255, 255]);
+        assert_eq!(palette[215], [0xee, 0, 0, 255]);
+        assert_eq!(palette[254], [0x11, 0x11, 0x11, 255]);
+        assert_eq!(palette[255], [0, 0, 0, 0]);
+
+        // color 7 = 0xffffccff en la paleta por defecto: no blanco
+        let cubes = parse_vox(&vox_file(&model((1, 1, 1), &[[0, 0, 0, 7]])), Vector3::zero(), 1.0).unwrap();
+        let albedo = cubes[0].material.albedo;
+        assert!(close(albedo.r, 1.0) && albedo.g < 0.7 && close(albedo.b, 1.0));
+    }
+
+    #[test]
+    fn transform_node_moves_the_model() {
+        let mut chunks = model((1, 1, 1), &[[0, 0, 0, 1]]);
+        let dict = |pairs: &[(&str, &str)]| {
+            let mut out = (pairs.len() as i32).to_le_bytes().to_vec();
+            for (k, v) in pairs {
+                out.extend((k.len() as i32).to_le_bytes());
+                out.extend(k.as_bytes());
+                out.extend((v.len() as i32).to_le_bytes());
+                out.extend(v.as_bytes());
+            }
+            out
+        };
+        // nTRN 0 -> nSHP 1 -> modelo 0, trasladado 4 en X
+        let mut trn = le_i32s(&[0]);
+        trn.extend(dict(&[]));
+        trn.extend(le_i32s(&[1, -1, 0, 1]));
+        trn.extend(dict(&[("_t", "4 0 0")]));
+        let mut shp = le_i32s(&[1]);
+        shp.extend(dict(&[]));
+        shp.extend(le_i32s(&[1, 0]));
+        shp.extend(dict(&[]));
+        chunks.push(chunk(b"nTRN", &trn, &[]));
+        chunks.push(chunk(b"nSHP", &shp, &[]));
+
+        let cubes = parse_vox(&vox_file(&chunks), Vector3::zero(), 1.0).unwrap();
+        assert_eq!(cubes.len(), 1);
+        assert_cube(&cubes[0], [4.5, 0.5, -0.5], 1.0);
+    }
+
+    #[test]
+    fn load_vox_places_the_model_at_the_given_scale() {
+        // 2x1x1: dos voxeles en X, el modelo se lee del archivo como desde la linea de comandos
+        // la base queda centrada en el centro entero del modelo (voxel 1 en X)
+        let file = TempFile::new("scale.vox", &vox_file(&model((2, 1, 1), &[[0, 0, 0, 1], [1, 0, 0, 1]])));
+        let cubes = load_vox(&file.path, Vector3::new(-1.0, 2.0, 3.0), 0.25).unwrap();
+        assert_eq!(cubes.len(), 2);
+        assert_cube(&cubes[0], [-1.125, 2.125, 2.875], 0.25);
+        assert_cube(&cubes[1], [-0.875, 2.125, 2.875], 0.25);
+    }
+
+    #[test]
+    fn rotation_byte_decodes_to_a_permutation() {
+        assert_eq!(decode_rotation(4), IDENTITY);
+        // X <- -Y, Y <- X
+        assert_eq!(decode_rotation(0b0001_0001), [[0, -1, 0], [1, 0, 0], [0, 0, 1]]);
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let mut data = vox_file(&model((1, 1, 1), &[[0, 0, 0, 1]]));
+        data.truncate(data.len() - 3);
+        assert!(parse_vox(&data, Vector3::zero(), 1.0).is_err());
+    }
+}